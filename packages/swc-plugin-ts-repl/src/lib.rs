@@ -1,26 +1,20 @@
-use std::env;
+pub mod rewrite_all_to_var;
+pub mod tla;
+
+use serde::Deserialize;
 use swc_core::common::comments::SingleThreadedComments;
-use swc_core::common::errors::{ColorConfig, Handler};
-use swc_core::common::SourceMap;
-use swc_core::common::sync::Lrc;
 use swc_core::ecma::ast::*;
-use swc_core::ecma::{
-    ast::Program,
-    transforms::testing::test,
-    visit::{as_folder, FoldWith, VisitMut},
-};
-use swc_core::ecma::parser::lexer::Lexer;
-use swc_core::ecma::parser::Syntax;
-use swc_core::plugin::{plugin_transform, proxies::TransformPluginProgramMetadata};
+use swc_core::ecma::visit::{Fold, FoldWith, VisitMut, VisitMutWith};
+#[cfg(feature = "plugin")]
+use swc_core::plugin::{errors::HANDLER, plugin_transform, proxies::TransformPluginProgramMetadata};
 use swc_core::ecma::transforms::module::common_js::*;
 use swc_core::common::Mark;
 use swc_core::ecma::transforms::base::feature::FeatureFlag;
-use swc_core::common::chain;
+use crate::rewrite_all_to_var::{MakeExportsConfigurable, TransformAllToVar};
+use crate::tla::transform_top_level_await;
 
 #[derive(Default)]
-pub struct TransformImportNamedToDestructuringRequireVisitor{
-    pub has_export_star:Option<bool>,
-}
+pub struct TransformImportNamedToDestructuringRequireVisitor;
 
 impl TransformImportNamedToDestructuringRequireVisitor {
     pub fn handle_import_decl(&mut self, decl: &mut ModuleDecl) -> Option<(ModuleItem, Vec<ModuleItem>)> {
@@ -47,6 +41,10 @@ impl TransformImportNamedToDestructuringRequireVisitor {
         let mut props = vec![];
         // from here
         let module_name = &n.src.value.to_string();
+        // generated nodes reuse the import's spans so source maps still
+        // point at the original import statement
+        let span = n.span;
+        let src_span = n.src.span;
 
         let mut import_default_name: Option<Ident> = None;
 
@@ -56,27 +54,13 @@ impl TransformImportNamedToDestructuringRequireVisitor {
                     if x.is_type_only {
                         return None;
                     }
-                    let alias: Option<Ident>;
-                    let name: Ident;
-                    match &x.imported {
-                        None => {
-                            alias = None;
-                            // this is fine
-                            name = x.clone().local
-                        }
-                        Some(x1) => {
-                            // this is fine
-                            alias = Some(x.clone().local);
-                            match x1 {
-                                // this is fine
-                                ModuleExportName::Ident(i) => name = i.clone(),
-                                ModuleExportName::Str(_) => {
-                                    unreachable!()
-                                }
-                            }
-                        }
-                    }
-                    props.push((name, alias));
+                    // the name it is exported as, when it's not the local one
+                    let imported = x.imported.as_ref().map(|imported| match imported {
+                        ModuleExportName::Ident(i) => PropName::Ident(i.clone()),
+                        // import {"a-b" as ab} from 'c' => const {"a-b": ab} = require('c')
+                        ModuleExportName::Str(s) => PropName::Str(s.clone()),
+                    });
+                    props.push((imported, x.local.clone()));
                 }
                 ImportSpecifier::Namespace(s) => {
                     import_default_name = Some(s.local.clone());
@@ -91,27 +75,27 @@ impl TransformImportNamedToDestructuringRequireVisitor {
         if import_default_name.is_some() {
             let used = vec![
                 ModuleItem::Stmt(Stmt::Expr(ExprStmt {
-                    span: Default::default(),
+                    span: import_default_name.as_ref().unwrap().span,
                     expr: Box::new(Expr::Ident(import_default_name.clone().unwrap())),
                 }))
             ];
             let dec = VarDecl {
-                span: Default::default(),
+                span,
                 kind: VarDeclKind::Const,
                 declare: false,
                 decls: vec![VarDeclarator {
-                    span: Default::default(),
+                    span,
                     definite: false,
                     name: Pat::Ident(BindingIdent {
                         type_ann: None,
                         id: import_default_name.unwrap(),
                     }),
                     init: Some(Box::new(Expr::Call(CallExpr {
-                        span: Default::default(),
+                        span,
                         args: vec![ExprOrSpread {
                             spread: None,
                             expr: Box::new(Expr::Lit(Lit::Str(Str {
-                                span: Default::default(),
+                                span: src_span,
                                 raw: None,
                                 value: module_name.clone().into(),
                             }))),
@@ -133,36 +117,34 @@ impl TransformImportNamedToDestructuringRequireVisitor {
         }
 
         let dec = VarDecl {
-            span: Default::default(),
+            span,
             kind: VarDeclKind::Const,
             declare: false,
             decls: vec![VarDeclarator {
-                span: Default::default(),
+                span,
                 // true or false?
                 definite: false,
                 name: Pat::Object(ObjectPat {
-                    span: Default::default(),
+                    span,
                     props: props
                         .iter()
                         .map(|x| {
-                            let (name, alias) = x;
-                            if alias.is_none() {
-                                used_names.push(name.clone());
-                                ObjectPatProp::Assign(AssignPatProp {
+                            let (imported, local) = x;
+                            used_names.push(local.clone());
+                            match imported {
+                                None => ObjectPatProp::Assign(AssignPatProp {
                                     span: Default::default(),
-                                    key: name.clone(),
+                                    key: local.clone(),
                                     value: None,
-                                })
-                            } else {
-                                used_names.push(alias.clone().unwrap());
-                                ObjectPatProp::KeyValue(KeyValuePatProp {
+                                }),
+                                Some(imported) => ObjectPatProp::KeyValue(KeyValuePatProp {
                                     // this is fine
-                                    key: PropName::Ident(name.clone()),
+                                    key: imported.clone(),
                                     value: Box::new(Pat::Ident(BindingIdent {
                                         type_ann: None,
-                                        id: alias.clone().unwrap(),
+                                        id: local.clone(),
                                     })),
-                                })
+                                }),
                             }
                         })
                         .collect(),
@@ -170,11 +152,11 @@ impl TransformImportNamedToDestructuringRequireVisitor {
                     type_ann: None,
                 }),
                 init: Some(Box::new(Expr::Call(CallExpr {
-                    span: Default::default(),
+                    span,
                     args: vec![ExprOrSpread {
                         spread: None,
                         expr: Box::new(Expr::Lit(Lit::Str(Str {
-                            span: Default::default(),
+                            span: src_span,
                             raw: None,
                             value: module_name.clone().into(),
                         }))),
@@ -190,7 +172,7 @@ impl TransformImportNamedToDestructuringRequireVisitor {
         // let usage = "";
         let name_statements: Vec<ModuleItem> = used_names.into_iter().map(|ident| {
             ModuleItem::Stmt(Stmt::Expr(ExprStmt {
-                span: Default::default(),
+                span: ident.span,
                 expr: Box::new(Expr::Ident(ident)),
             }
             ))
@@ -213,10 +195,6 @@ impl VisitMut for TransformImportNamedToDestructuringRequireVisitor {
                     // ModuleDecl::ExportNamed(_) => {}
                     // ModuleDecl::ExportDefaultDecl(_) => {}
                     // ModuleDecl::ExportDefaultExpr(_) => {}
-                    ModuleDecl::ExportAll(all) => {
-                        self.has_export_star=Some(true);
-                        return true;
-                    }
                     // ModuleDecl::TsImportEquals(_) => {}
                     // ModuleDecl::TsExportAssignment(_) => {}
                     // ModuleDecl::TsNamespaceExport(_) => {}
@@ -272,6 +250,75 @@ impl VisitMut for TransformImportNamedToDestructuringRequireVisitor {
 //     }
 // }
 
+/// Which of the REPL passes the plugin applies, from the plugin's JSON config.
+/// They run in the order below, the same order ts-repl-transpile runs them.
+/// swc runs plugins before its own module transform, so the passes that work
+/// on CommonJS output need `commonjs` as well.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Config {
+    /// Rewrite imports to `require` destructuring (default true)
+    pub import_rewrite: bool,
+    /// Lower the module to CommonJS in the plugin (default false)
+    pub commonjs: bool,
+    /// Declare everything with `var` so it can be redeclared (default false)
+    pub all_to_var: bool,
+    /// Make the exports `commonjs` defines configurable (default false)
+    pub exports_configurable: bool,
+    /// Wrap modules with a top level await in an async iife, hoisting their
+    /// declarations out of it (default false)
+    pub top_level_await: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            import_rewrite: true,
+            commonjs: false,
+            all_to_var: false,
+            exports_configurable: false,
+            top_level_await: false,
+        }
+    }
+}
+
+pub struct ReplTransform {
+    config: Config,
+    unresolved_mark: Mark,
+}
+
+pub fn repl_transform(config: Config, unresolved_mark: Mark) -> ReplTransform {
+    ReplTransform { config, unresolved_mark }
+}
+
+impl Fold for ReplTransform {
+    fn fold_module(&mut self, mut module: Module) -> Module {
+        if self.config.import_rewrite {
+            module.visit_mut_with(&mut TransformImportNamedToDestructuringRequireVisitor);
+        }
+        if self.config.commonjs {
+            module = module.fold_with(&mut common_js::<SingleThreadedComments>(
+                self.unresolved_mark,
+                Default::default(),
+                FeatureFlag::default(),
+                None,
+            ));
+        }
+        if self.config.all_to_var {
+            module.visit_mut_with(&mut TransformAllToVar);
+        }
+        if self.config.exports_configurable {
+            module.visit_mut_with(&mut MakeExportsConfigurable);
+        }
+        if self.config.top_level_await {
+            if let Some(rewritten) = transform_top_level_await(&module).module {
+                module = rewritten;
+            }
+        }
+        module
+    }
+}
+
 /// An example plugin function with macro support.
 /// `plugin_transform` macro interop pointers into deserialized structs, as well
 /// as returning ptr back to host.
@@ -287,87 +334,231 @@ impl VisitMut for TransformImportNamedToDestructuringRequireVisitor {
 ///
 /// This requires manual handling of serialization / deserialization from ptrs.
 /// Refer swc_plugin_macro to see how does it work internally.
+#[cfg(feature = "plugin")]
 #[plugin_transform]
-pub fn process_transform(program: Program, _metadata: TransformPluginProgramMetadata) -> Program {
-    program.fold_with(&mut as_folder(
-        TransformImportNamedToDestructuringRequireVisitor{
-            ..Default::default()
+pub fn process_transform(program: Program, metadata: TransformPluginProgramMetadata) -> Program {
+    let config: Config = match metadata.get_transform_plugin_config() {
+        Some(json) => match serde_json::from_str(&json) {
+            Ok(config) => config,
+            Err(err) => {
+                // the host shows this like any other diagnostic, instead of
+                // the plugin aborting on a mistake in the user's config
+                HANDLER.with(|handler| {
+                    handler.err(&format!("invalid config for swc-plugin-ts-repl: {}", err))
+                });
+                return program;
+            }
         },
-    ))
+        None => Config::default(),
+    };
+    program.fold_with(&mut repl_transform(config, metadata.unresolved_mark))
 }
 
-test!(
-    Default::default(),
-    |_| as_folder(TransformImportNamedToDestructuringRequireVisitor),
-    transform_all,
-    r#"
-    import {abc as xyz} from 'foobar';
-    import * as myns from 'other'
-    import foo from 'bar';
-    console.log("transform",xyz,foo,myns);
-    "#
-    .trim(),
-    r#"
-    const { abc: xyz  } = require("foobar");
-    const myns = require("other");
-    const foo = require("bar");
-    console.log("transform", xyz, foo, myns);
-    "#
-    .trim()
-);
-
-test!(
-    Syntax::Typescript(Default::default()),
-    |_| as_folder(TransformImportNamedToDestructuringRequireVisitor),
-    transform_all_when_not_referenced,
-    r#"
-    import {abc as xyz} from 'foobar';
-    import * as myns from 'other'
-    import foo from 'bar';
-    "#
-    .trim(),
-    r#"
-    const { abc: xyz  } = require("foobar");
-    const myns = require("other");
-    const foo = require("bar");
-    "#
-    .trim()
-);
-
-test!(
-    Syntax::Typescript(Default::default()),
-    |_|
-    chain!(
-      as_folder(TransformImportNamedToDestructuringRequireVisitor),
-      common_js::<SingleThreadedComments>(
-            Mark::new(),
-            Default::default(),
-            FeatureFlag::default(),
-            None
-        )
-    ) ,
-    transform_exports,
-    r#"
-    import {bar} from 'baz';
-    export const foo = 42;
-    "#
-    .trim(),
-    r#"
-     "use strict";
-Object.defineProperty(exports, "__esModule", {
-    value: true
-});
-Object.defineProperty(exports, "foo", {
-    enumerable: true,
-    get: function() {
-        return foo;
-    }
-});
-    const { bar  } = require("baz");
-    const foo = 42;
-    "#
-    .trim()
-);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use swc_core::common::chain;
+    use swc_core::ecma::parser::Syntax;
+    use swc_core::ecma::transforms::testing::test;
+    use swc_core::ecma::visit::as_folder;
+
+    test!(
+        Default::default(),
+        |_| as_folder(TransformImportNamedToDestructuringRequireVisitor),
+        transform_all,
+        r#"
+        import {abc as xyz} from 'foobar';
+        import * as myns from 'other'
+        import foo from 'bar';
+        console.log("transform",xyz,foo,myns);
+        "#
+        .trim(),
+        r#"
+        const { abc: xyz  } = require("foobar");
+        const myns = require("other");
+        const foo = require("bar");
+        console.log("transform", xyz, foo, myns);
+        "#
+        .trim()
+    );
+
+    test!(
+        Syntax::Typescript(Default::default()),
+        |_| as_folder(TransformImportNamedToDestructuringRequireVisitor),
+        transform_all_when_not_referenced,
+        r#"
+        import {abc as xyz} from 'foobar';
+        import * as myns from 'other'
+        import foo from 'bar';
+        "#
+        .trim(),
+        r#"
+        const { abc: xyz  } = require("foobar");
+        const myns = require("other");
+        const foo = require("bar");
+        "#
+        .trim()
+    );
+
+    test!(
+        Default::default(),
+        |_| as_folder(TransformImportNamedToDestructuringRequireVisitor),
+        transform_string_import_names,
+        r#"
+        import {"a-b" as ab, default as c} from 'foobar';
+        console.log(ab, c);
+        "#
+        .trim(),
+        r#"
+        const { "a-b": ab, default: c } = require("foobar");
+        console.log(ab, c);
+        "#
+        .trim()
+    );
+
+    test!(
+        Syntax::Typescript(Default::default()),
+        |_|
+        chain!(
+          as_folder(TransformImportNamedToDestructuringRequireVisitor),
+          common_js::<SingleThreadedComments>(
+                Mark::new(),
+                Default::default(),
+                FeatureFlag::default(),
+                None
+            )
+        ) ,
+        transform_exports,
+        r#"
+        import {bar} from 'baz';
+        export const foo = 42;
+        "#
+        .trim(),
+        r#"
+         "use strict";
+    Object.defineProperty(exports, "__esModule", {
+        value: true
+    });
+    Object.defineProperty(exports, "foo", {
+        enumerable: true,
+        get: function() {
+            return foo;
+        }
+    });
+        const { bar  } = require("baz");
+        const foo = 42;
+        "#
+        .trim()
+    );
+
+    test!(
+        Syntax::Typescript(Default::default()),
+        |_| repl_transform(
+            Config {
+                all_to_var: true,
+                ..Default::default()
+            },
+            Mark::new()
+        ),
+        all_to_var,
+        r#"
+        const a = 1;
+        let b = 2;
+        "#
+        .trim(),
+        r#"
+        var a = 1;
+        var b = 2;
+        "#
+        .trim()
+    );
+
+    test!(
+        Syntax::Typescript(Default::default()),
+        |_| repl_transform(
+            Config {
+                top_level_await: true,
+                ..Default::default()
+            },
+            Mark::new()
+        ),
+        top_level_await,
+        r#"
+        const foo = async () => 42;
+        await foo();
+        "#
+        .trim(),
+        r#"
+        var foo;
+        (async ()=>{
+            foo = async ()=>42;
+            return await foo();
+        })();
+        "#
+        .trim()
+    );
+
+    test!(
+        Syntax::Typescript(Default::default()),
+        |_| repl_transform(
+            Config {
+                top_level_await: true,
+                ..Default::default()
+            },
+            Mark::new()
+        ),
+        top_level_await_declarations,
+        r#"
+        let x;
+        var y;
+        const a = 1, b = await Promise.resolve(2);
+        "#
+        .trim(),
+        r#"
+        var x, y, a, b;
+        (async ()=>{
+            x = void 0;
+            a = 1;
+            b = await Promise.resolve(2);
+        })();
+        "#
+        .trim()
+    );
+
+    test!(
+        Default::default(),
+        |_| repl_transform(
+            Config {
+                commonjs: true,
+                all_to_var: true,
+                exports_configurable: true,
+                ..Default::default()
+            },
+            Mark::new()
+        ),
+        commonjs_exports_configurable,
+        r#"
+        export const foo = 42;
+        "#
+        .trim(),
+        r#"
+        "use strict";
+        Object.defineProperty(exports, "__esModule", {
+            value: true,
+            configurable: true
+        });
+        Object.defineProperty(exports, "foo", {
+            enumerable: true,
+            get: function() {
+                return foo;
+            },
+            configurable: true
+        });
+        var foo = 42;
+        "#
+        .trim()
+    );
+}
 
 // #[test]
 // fn main() {
@@ -56,10 +56,14 @@ impl VisitMut for TransformAllToVar {
   //         value: Box::new(true.into()),
   //     }))));
   // }
+}
 
-  fn visit_mut_module_items(&mut self, n: &mut Vec<ModuleItem>) {
-    n.visit_mut_children_with(self);
+/// Makes the exports the CommonJS transform defines configurable, so
+/// evaluating the same export twice redefines it instead of throwing
+pub struct MakeExportsConfigurable;
 
+impl VisitMut for MakeExportsConfigurable {
+  fn visit_mut_module_items(&mut self, n: &mut Vec<ModuleItem>) {
     // Only process the first function declaration called _exports
     // As far as I can tell this is always the one that is generated by swc
     // we want to alter the Object.defineProperty call in it and add the
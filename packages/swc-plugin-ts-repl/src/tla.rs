@@ -1,8 +1,7 @@
-use swc_core::base::atoms::JsWord;
+use swc_core::ecma::atoms::JsWord;
 use swc_core::common::DUMMY_SP;
-use swc_core::common::util::take::Take;
-use swc_core::ecma::ast::{ArrowExpr, AssignExpr, BindingIdent, BlockStmt, BlockStmtOrExpr, CallExpr, Decl, Expr, ExprStmt, FnExpr, Function, Ident, KeyValueProp, Module, ModuleItem, ObjectPatProp, op, ParenExpr, Pat, Prop, PropOrSpread, ReturnStmt, Stmt, VarDecl, VarDeclarator, VarDeclKind};
-use swc_core::ecma::utils::{contains_top_level_await, ExprFactory, };
+use swc_core::ecma::ast::{ArrowExpr, AssignExpr, BindingIdent, BlockStmt, BlockStmtOrExpr, CallExpr, Decl, Expr, ExprStmt, Ident, Module, ModuleItem, ObjectPatProp, op, ParenExpr, Pat, ReturnStmt, Stmt, VarDecl, VarDeclarator, VarDeclKind};
+use swc_core::ecma::utils::{contains_top_level_await, undefined, ExprFactory, };
 use swc_core::ecma::visit::Visit;
 use swc_core::ecma::visit::VisitWith;
 
@@ -114,30 +113,50 @@ impl Visit for TransformTopLevelAwait {
 
         n.iter().for_each(|x| match x {
             ModuleItem::Stmt(x) => {
-                let mut rewritten_stmt:Option<Stmt> = None;
+                let mut rewritten_stmts: Option<Vec<Stmt>> = None;
                 match x {
                     Stmt::Decl(y) => {
                         match y {
                             Decl::Class(x) => { self.top_level_syms.push(x.ident.sym.clone()); }
                             Decl::Fn(x) => { self.top_level_syms.push(x.ident.sym.clone()); }
                             Decl::Var(x) => {
+                                let mut assignments = vec![];
                                 x.decls.iter().for_each(|decl| {
                                     decl.name.visit_with(self);
+                                    // without an initializer `var x;` leaves x as it was,
+                                    // while `let x;` makes it undefined again
+                                    let init = match &decl.init {
+                                        Some(init) => init.clone(),
+                                        None if x.kind == VarDeclKind::Var => return,
+                                        None => undefined(decl.span),
+                                    };
                                     // all of these things need to be defined outside the iffe
                                     // and all of them may (as well) return a promise that the caller/user would like to evaluate to a value
                                     // or an exception
                                     //
                                     // i.e. any top level statement is now an assignment to forwardly declared variables
-                                    rewritten_stmt = Some(Stmt::Expr(ExprStmt {
-                                        span: DUMMY_SP,
-                                        expr: Box::new(Expr::Assign(AssignExpr {
-                                            op: op!("="),
-                                            span: DUMMY_SP,
-                                            left: decl.name.clone().into(),
-                                            right: decl.init.as_ref().unwrap().clone(),
-                                        })),
+                                    //
+                                    // keep the original spans so source maps still point at the declaration
+                                    let mut expr = Expr::Assign(AssignExpr {
+                                        op: op!("="),
+                                        span: decl.span,
+                                        left: decl.name.clone().into(),
+                                        right: init,
+                                    });
+                                    // a statement starting with `{` is a block, so `{a} = b;`
+                                    // only assigns when it's wrapped in parens
+                                    if let Pat::Object(_) = decl.name {
+                                        expr = Expr::Paren(ParenExpr {
+                                            span: decl.span,
+                                            expr: Box::new(expr),
+                                        });
+                                    }
+                                    assignments.push(Stmt::Expr(ExprStmt {
+                                        span: x.span,
+                                        expr: Box::new(expr),
                                     }));
                                 });
+                                rewritten_stmts = Some(assignments);
                             }
                             _ => {}
                         }
@@ -146,10 +165,9 @@ impl Visit for TransformTopLevelAwait {
                 }
                 // put the original into the iffe we're building up...
                 // can't decide whether visit or visit mut is best here
-                if rewritten_stmt.is_some() {
-                    async_iife.push(rewritten_stmt.unwrap());
-                } else {
-                    async_iife.push(x.clone());
+                match rewritten_stmts {
+                    Some(stmts) => async_iife.extend(stmts),
+                    None => async_iife.push(x.clone()),
                 }
             }
             ModuleItem::ModuleDecl(x) => {
@@ -188,7 +206,7 @@ impl Visit for TransformTopLevelAwait {
                         if x.expr.is_await_expr() {
                             let i = async_iife.len() - 1;
                             async_iife[i] = Stmt::Return(ReturnStmt {
-                                span: DUMMY_SP,
+                                span: x.span,
                                 arg: Some(Box::new(x.expr.as_ref().clone())),
                             });
                         }
@@ -52,3 +52,39 @@ console.log('hello world', xyz, foo, myns);
       throw e
     }
   })
+
+swc
+  .transform(`const foo = async () => 42;\nconst bar = await foo();`, {
+    filename: "input.ts",
+    isModule: true,
+    jsc: {
+      parser: {
+        syntax: "typescript",
+      },
+      experimental: {
+        plugins: [
+          [
+            "@fit2/swc-plugin-ts-repl",
+            { allToVar: true, topLevelAwait: true },
+          ],
+        ],
+      },
+      target: "es2022",
+    },
+  })
+  .then((output) => {
+    const expected = `var foo, bar;
+(async ()=>{
+    foo = async ()=>42;
+    bar = await foo();
+})();
+`
+    try {
+      assert(output.code === expected)
+    } catch (e) {
+      console.error("Expected:\n\n", expected)
+      console.error(">>\n\n")
+      console.error("Actual:\n\n", output.code)
+      throw e
+    }
+  })
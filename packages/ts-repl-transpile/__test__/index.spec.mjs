@@ -1,6 +1,27 @@
 import test from "ava";
+import vm from "node:vm";
+import fs from "node:fs";
+import os from "node:os";
+import path from "node:path";
 
-import {transformSync, evaluableSpans, transformSyncRegular} from "../index.js";
+import {
+    transformSync,
+    evaluableSpans,
+    transformSyncRegular,
+    mapStackTrace,
+    transform,
+    evaluableSpansAsync,
+    selectForms,
+    evaluableSelections,
+    explainTransform,
+    NodeKind,
+    setLogger,
+    clearLogger,
+    ReplSession
+} from "../index.js";
+
+// where a span is and what it is, leaving out the rest
+const location = ({start, end, type}) => ({start, end, type});
 
 const makeSpanTestInput = (s) => {
     const pos = s.indexOf("|");
@@ -87,7 +108,7 @@ export const foo = (a: number, b: number): Promise<E.Either<unknown,never>> =>
         ...input
     );
     console.log("the output", output);
-    t.deepEqual(output.spans[output.spans.length - 1], {
+    t.deepEqual(location(output.spans[output.spans.length - 1]), {
         // is this off off?
         // end: 85,
         // start: 83,
@@ -109,7 +130,7 @@ const what = {|
         ...input
     );
     console.log("the output", output);
-    t.deepEqual(output.spans[output.spans.length - 1], {
+    t.deepEqual(location(output.spans[output.spans.length - 1]), {
         end: 53,
         start: 14,
         type: "ObjectExpression"
@@ -125,7 +146,7 @@ test("evaluable spans - property path", (t) => {
         ...input
     );
     console.log("the output", output);
-    t.deepEqual(output.spans[output.spans.length - 1], {
+    t.deepEqual(location(output.spans[output.spans.length - 1]), {
         end: 4,
         start: 1,
         type: "MemberExpression"
@@ -140,7 +161,7 @@ test("evaluable spans - function param", (t) => {
         ...input
     );
     console.log("the output", output);
-    t.deepEqual(output.spans[output.spans.length - 1], {
+    t.deepEqual(location(output.spans[output.spans.length - 1]), {
         end: 13,
         start: 12,
         type: "Identifier"
@@ -167,13 +188,78 @@ test("evaluable spans - should do something reasonable for call exprs", (t) => {
         ...input
     );
     console.log("the output", output);
-    t.deepEqual(output.spans, [{
+    t.deepEqual(output.spans.map(location), [{
         end: 20,
         start: 1,
         type: "ExpressionStatement"
     }, {end: 20, start: 1, type: "CallExpression"}]);
 });
 
+test("evaluable spans - parents, depths and previews", (t) => {
+    const output = evaluableSpans(...makeSpanTestInput(`a.b|.c.d`));
+    t.deepEqual(output.spans.map(({type, parent, depth, preview}) => ({type, parent, depth, preview})), [
+        {type: NodeKind.ExpressionStatement, parent: undefined, depth: 0, preview: "a.b.c.d"},
+        {type: NodeKind.MemberExpression, parent: 0, depth: 1, preview: "a.b.c.d"},
+        {type: NodeKind.MemberExpression, parent: 1, depth: 2, preview: "a.b.c"},
+        {type: NodeKind.MemberExpression, parent: 2, depth: 3, preview: "a.b"},
+    ]);
+});
+
+test("evaluable spans - declared names", (t) => {
+    const output = evaluableSpans(...makeSpanTestInput(`const foo = 4|2;`));
+    t.deepEqual(output.spans.map((s) => [s.type, s.name]), [
+        ["VariableDeclaration", "foo"],
+        ["VariableDeclarator", "foo"],
+        ["NumericLiteral", undefined],
+    ]);
+});
+
+test("evaluable spans - switch statements are not if statements", (t) => {
+    const output = evaluableSpans(...makeSpanTestInput(`switch (x) { case 1: f|oo(); }`));
+    t.is(output.spans[0].type, "SwitchStatement");
+});
+
+test("evaluable spans - utf16 offsets like TextDocument.offsetAt", (t) => {
+    const [source, offset] = makeSpanTestInput(`const s = "é😀"; foo(1|)`);
+    const output = evaluableSpans(source, offset, {offsetEncoding: "utf16"});
+    t.deepEqual(output.spans.map(location)[0], {start: 17, end: 23, type: NodeKind.ExpressionStatement});
+    t.is(source.slice(output.spans[0].start, output.spans[0].end), "foo(1)");
+});
+
+test("evaluable spans - positions and ranges", (t) => {
+    const source = `const s = "é";\r\nfoo(1)`;
+    const output = evaluableSpans(source, {line: 1, character: 5}, {offsetEncoding: "lineColumn"});
+    t.deepEqual(output.spans[0].range, {start: {line: 1, character: 0}, end: {line: 1, character: 6}});
+    t.throws(() => evaluableSpans(source, 20, {offsetEncoding: "lineColumn"}), {message: /position/});
+});
+
+test("select forms - expand from a cursor and from a selection", (t) => {
+    const source = `foo(a.b.c)`;
+    const options = {offsetEncoding: "utf16"};
+    const cursor = selectForms(source, source.indexOf(".c"), undefined, options);
+    t.deepEqual(location(cursor.spans[cursor.expand]), {start: 4, end: 7, type: NodeKind.MemberExpression});
+    t.deepEqual(location(cursor.spans[cursor.topLevel]), {start: 0, end: 10, type: NodeKind.ExpressionStatement});
+
+    const selection = selectForms(source, 4, 7, options);
+    t.deepEqual(location(selection.spans[selection.expand]), {start: 4, end: 9, type: NodeKind.MemberExpression});
+    t.deepEqual(location(selection.spans[selection.shrink]), {start: 4, end: 5, type: NodeKind.Identifier});
+});
+
+test("evaluable selections - snap each selection to the forms covering it", (t) => {
+    const source = `let a = 1;\nlet b = 2;\nfoo(a)`;
+    const {selections} = evaluableSelections(source, [
+        {start: {line: 0, character: 4}, end: {line: 1, character: 5}},
+        {start: {line: 2, character: 6}, end: {line: 2, character: 6}},
+    ], {offsetEncoding: "lineColumn"});
+    t.deepEqual(selections[0].forms.map(location), [
+        {start: 0, end: 10, type: NodeKind.VariableDeclaration},
+        {start: 11, end: 21, type: NodeKind.VariableDeclaration},
+    ]);
+    t.true(selections[0].cutsForm);
+    t.deepEqual(selections[1].forms.map(location), [{start: 22, end: 28, type: NodeKind.CallExpression}]);
+    t.false(selections[1].cutsForm);
+});
+
 test("top-level await", (t) => {
     const input = `const foo = async () => 42;
   const bar = await foo()`;
@@ -289,4 +375,390 @@ function _exportStar(from, to) {
     return from;
 }`
     })
-})
\ No newline at end of file
+})
+
+test("options - strict mode and module interop", (t) => {
+    const output = transformSync(`export const foo = 42;`, {strictMode: true, module: {type: "commonjs"}});
+    console.log("options - the output", output);
+    t.deepEqual(output.code.startsWith("\"use strict\""), true);
+});
+
+test("options - invalid values are rejected", (t) => {
+    t.throws(() => transformSync(`const foo = 42;`, {target: "es1999"}), {message: /target/});
+    t.throws(() => transformSync(`const foo = 42;`, {syntax: "coffeescript"}), {message: /syntax/});
+});
+
+test("options - ecmascript syntax", (t) => {
+    const output = transformSyncRegular(`const foo = 42;`, {syntax: "ecmascript", target: "es2020"});
+    t.deepEqual(output, {code: `const foo = 42;\n`});
+});
+
+test("diagnostics - invalid code returns diagnostics instead of throwing", (t) => {
+    const output = transformSync(`const foo = ;`);
+    console.log("diagnostics - the output", output);
+    t.deepEqual(output.code, "");
+    t.deepEqual(output.isAsync, false);
+    t.true(output.diagnostics.length > 0);
+    t.deepEqual(output.diagnostics[0].severity, "error");
+    t.like(output.diagnostics[0].span, {start: 12, end: 13, startLine: 1, startColumn: 12});
+});
+
+test("diagnostics - evaluable spans report why there are none", (t) => {
+    const output = evaluableSpans(`i am invalid code`, 3);
+    t.deepEqual(output.spans, []);
+    t.deepEqual(output.diagnostics[0].severity, "error");
+});
+
+test("diagnostics - evaluable spans are still found in the statements that parse", (t) => {
+    const [source, offset] = makeSpanTestInput(`foo(\nconst b = [1, |2];\n`);
+    const output = evaluableSpans(source, offset, {offsetEncoding: "utf16"});
+    t.deepEqual(output.spans.map(location).at(-1), {start: 15, end: 21, type: NodeKind.ArrayLiteral});
+    t.true(output.diagnostics.every((d) => d.span.start <= 5));
+});
+
+test("source maps - only returned when asked for", (t) => {
+    const output = transformSync(`const foo: number = 42;`);
+    t.deepEqual(output.map, undefined);
+});
+
+test("source maps - map the repl output back into the document", (t) => {
+    const input = `const foo = async () => 42;
+  const bar = await foo()`;
+    const output = transformSync(input, {sourceMaps: true, lineOffset: 10, columnOffset: 4});
+    const map = JSON.parse(output.map);
+    console.log("source maps - the map", map);
+    t.deepEqual(map.version, 3);
+    t.deepEqual(map.sourcesContent, [input]);
+    t.true(map.mappings.length > 0);
+});
+
+test("map stack trace - frames point at the original document", (t) => {
+    const input = `const foo: number = 1;
+throw new Error("boom")`;
+    const output = transformSync(input, {sourceMaps: true, lineOffset: 4});
+    let error;
+    try {
+        vm.runInNewContext(output.code, {exports: {}}, {filename: "doc.ts"});
+    } catch (e) {
+        error = e;
+    }
+    const mapped = mapStackTrace(error.stack, output.map, "doc.ts");
+    console.log("map stack trace - the output", mapped);
+    t.like(mapped.frames[0], {file: "doc.ts", line: 6, mapped: true});
+    t.true(mapped.stack.includes("doc.ts:6:"));
+});
+
+test("map stack trace - leaves other frames alone", (t) => {
+    const output = transformSync(`1`, {sourceMaps: true});
+    const stack = `Error: boom\n    at foo (other.js:10:2)`;
+    const mapped = mapStackTrace(stack, output.map, "doc.ts");
+    t.deepEqual(mapped.stack, stack);
+    t.deepEqual(mapped.frames, [{functionName: "foo", file: "other.js", line: 10, column: 2, mapped: false}]);
+});
+
+test("async - transform resolves to the same output as transformSync", async (t) => {
+    const input = `const foo = async () => 42;
+  await foo()`;
+    t.deepEqual(await transform(input), transformSync(input));
+});
+
+test("async - evaluableSpansAsync resolves to the same output as evaluableSpans", async (t) => {
+    const input = makeSpanTestInput(`foo.bar.baz('heyo')|`);
+    t.deepEqual(await evaluableSpansAsync(...input), evaluableSpans(...input));
+});
+
+test("session - records bindings and reports redeclarations", (t) => {
+    const session = new ReplSession();
+    const first = session.transform("ns", `const a = 1; function b() {}`);
+    t.deepEqual(first.declared, ["a", "b"]);
+    t.deepEqual(first.redeclared, []);
+    const second = session.transform("ns", `let a = 2`);
+    t.deepEqual(second.redeclared, ["a"]);
+    t.deepEqual(session.bindings("ns"), ["a", "b"]);
+    t.deepEqual(session.bindings("other"), []);
+});
+
+test("session - inlines const enums declared by earlier evaluations", (t) => {
+    const session = new ReplSession();
+    session.transform("ns", `const enum Color { Red, Green = "green", Blue = 4, Purple }`);
+    t.deepEqual(session.constEnums("ns"), [{
+        name: "Color",
+        members: [
+            {name: "Blue", value: 4},
+            {name: "Green", value: "green"},
+            {name: "Purple", value: 5},
+            {name: "Red", value: 0},
+        ]
+    }]);
+    const output = session.transform("ns", `[Color.Red, Color.Green, Color["Purple"]]`);
+    console.log("session - the output", output);
+    t.deepEqual(vm.runInNewContext(output.code, {exports: {}}), [0, "green", 5]);
+});
+
+test("session - only installs the export star helper once", (t) => {
+    const session = new ReplSession();
+    const first = session.transform("ns", `export * from "foo"`);
+    t.true(first.code.includes("function _exportStar"));
+    t.deepEqual(session.helpers("ns"), ["exportStar"]);
+    const second = session.transform("ns", `export * from "bar"`);
+    t.false(second.code.includes("function _exportStar"));
+});
+
+test("session - nothing is recorded for code that fails to parse", (t) => {
+    const session = new ReplSession();
+    const output = session.transform("ns", `const a = `);
+    t.true(output.diagnostics.length > 0);
+    t.deepEqual(session.bindings("ns"), []);
+});
+
+test("session - namespace options, unmap and reset", (t) => {
+    const session = new ReplSession();
+    session.setOptions("ns", {strictMode: true});
+    t.true(session.transform("ns", `export const a = 1`).code.startsWith(`"use strict"`));
+    session.unmap("ns", "a");
+    t.deepEqual(session.bindings("ns"), []);
+    session.reset();
+    t.deepEqual(session.namespaces(), []);
+});
+
+test("session - round trips through json and disk", (t) => {
+    const session = new ReplSession();
+    session.setOptions("ns", {strictMode: true});
+    session.transform("ns", `const enum E { A = 1 }\nconst a = E.A`);
+    session.transform("ns", `const a = `);
+    session.transform("other", `function f() {}`);
+
+    const restored = ReplSession.fromJson(session.toJson());
+    t.deepEqual(restored.namespaces(), ["ns", "other"]);
+    t.deepEqual(restored.history("ns"), [{source: `const enum E { A = 1 }\nconst a = E.A`, declared: ["E", "a"]}]);
+    // what the old contexts defined is gone with them
+    t.deepEqual(restored.bindings("ns"), []);
+    t.deepEqual(restored.constEnums("ns"), []);
+    t.deepEqual(ReplSession.fromJson(restored.toJson()).toJson(), restored.toJson());
+
+    const file = path.join(fs.mkdtempSync(path.join(os.tmpdir(), "repl-session-")), "nested", "session.json");
+    session.save(file);
+    t.deepEqual(ReplSession.load(file).toJson(), restored.toJson());
+});
+
+test("session - rejects snapshots it can't read", (t) => {
+    t.throws(() => ReplSession.fromJson(`{"version": 0, "namespaces": {}}`), {message: /unsupported version/});
+    t.throws(() => ReplSession.fromJson(`nope`), {message: /Invalid session/});
+});
+
+test("filename - picks syntax from the extension", (t) => {
+    const tsx = transformSync(`const el = <div>{(x as any)}</div>`, {filename: "/src/view.tsx"});
+    t.deepEqual(tsx.diagnostics, undefined);
+    t.true(tsx.code.includes("React.createElement"));
+
+    const ts = transformSync(`const n = <number>x`, {filename: "/src/cast.mts"});
+    t.deepEqual(ts.diagnostics, undefined);
+
+    const js = transformSync(`const n: number = 1`, {filename: "/src/plain.js"});
+    t.true(js.diagnostics.length > 0);
+
+    const explicit = transformSync(`const n: number = 1`, {filename: "/src/plain.js", syntax: "typescript"});
+    t.deepEqual(explicit.diagnostics, undefined);
+});
+
+test("filename - names the file in source maps", (t) => {
+    const output = transformSync(`const a = 1`, {filename: "/src/doc.ts", sourceMaps: true});
+    t.deepEqual(JSON.parse(output.map).sources, ["/src/doc.ts"]);
+});
+
+test("jsx - classic runtime evaluates with React in scope", (t) => {
+    const output = transformSync(`const Greeting = ({name}: {name: string}) => <b>hi {name}</b>;
+<><Greeting name="you" /></>`, {syntax: "tsx"});
+    console.log("jsx - the output", output);
+    const React = {
+        Fragment: "Fragment",
+        createElement: (type, props, ...children) => ({type, props, children}),
+    };
+    const result = vm.runInNewContext(output.code, {exports: {}, React});
+    t.deepEqual(result.type, "Fragment");
+    t.deepEqual(result.children[0].props, {name: "you"});
+});
+
+test("jsx - classic runtime keeps the import its pragma needs", (t) => {
+    const source = `import React from "react";
+const Greeting = ({name}: {name: string}) => <b>hi {name}</b>;
+const el = <Greeting name="you" />;`;
+    const React = {createElement: (type, props, ...children) => ({type, props, children})};
+    const require = (id) => id === "react" ? React : undefined;
+    const context = {exports: {}, require};
+    vm.runInNewContext(transformSync(source, {filename: "/src/view.tsx"}).code, context);
+    t.deepEqual(context.el.props, {name: "you"});
+    const regular = transformSyncRegular(source, {filename: "/src/view.tsx"});
+    t.deepEqual(vm.runInNewContext(`${regular.code}\nel`, {exports: {}, require}).props, {name: "you"});
+});
+
+test("jsx - automatic runtime requires react/jsx-runtime", (t) => {
+    const output = transformSync(`<div className="a" />`, {syntax: "jsx", jsx: {runtime: "automatic"}});
+    console.log("jsx - the output", output);
+    t.true(output.code.includes(`require("react/jsx-runtime")`));
+    t.false(output.code.includes("import "));
+});
+
+test("jsx - invalid runtime throws", (t) => {
+    t.throws(() => transformSync(`<div />`, {syntax: "tsx", jsx: {runtime: "preact"}}), {message: /jsx\.runtime/});
+});
+
+test("evaluable spans - jsx elements, attributes and expression containers", (t) => {
+    const output = evaluableSpans(...makeSpanTestInput(`const el = <Foo bar={ba|z} />`), {syntax: "tsx"});
+    console.log("jsx spans", output);
+    t.deepEqual(output.spans.map((s) => s.type), [
+        "VariableDeclaration",
+        "VariableDeclarator",
+        "JSXElement",
+        "JSXAttribute",
+        "JSXExpressionContainer",
+        "Identifier",
+    ]);
+});
+
+test("es modules - keeps imports and exports every top level binding", (t) => {
+    const output = transformSync(`import {foo} from "bar";
+const a: number = await foo();
+export const b = 2;
+function c() {}`, {module: {type: "es"}});
+    console.log("es modules - the output", output);
+    t.true(output.isAsync);
+    t.true(output.code.includes(`import { foo } from "bar";`));
+    t.true(output.code.includes("const a = await foo();"));
+    t.true(output.code.includes("export { foo, a, c };"));
+    t.false(output.code.includes("require"));
+});
+
+test("es modules - session imports bindings from earlier snippets", (t) => {
+    const session = new ReplSession();
+    session.setOptions("ns", {module: {type: "es"}});
+    session.transform("ns", `const a = 1; const unused = 2`);
+    const output = session.transform("ns", `const b = a + 1`);
+    t.true(output.code.startsWith(`import { a } from "ns";`));
+    t.true(output.code.includes("export { b };"));
+    const redeclared = session.transform("ns", `const a = 3; a`);
+    t.false(redeclared.code.includes("import"));
+});
+
+(vm.SourceTextModule ? test : test.skip)("es modules - snippets link as vm.SourceTextModule", async (t) => {
+    const session = new ReplSession();
+    session.setOptions("ns", {module: {type: "es"}});
+    const context = vm.createContext({});
+    const exports = {};
+    const evaluate = async (source) => {
+        const mod = new vm.SourceTextModule(session.transform("ns", source).code, {context});
+        await mod.link((specifier) => {
+            const names = Object.keys(exports);
+            return new vm.SyntheticModule(names, function () {
+                names.forEach((name) => this.setExport(name, exports[name]));
+            }, {context});
+        });
+        await mod.evaluate();
+        Object.assign(exports, mod.namespace);
+        return mod.namespace;
+    };
+    await evaluate(`const a = 20`);
+    t.deepEqual((await evaluate(`const b = a + 22`)).b, 42);
+});
+
+test("plugins - a plugin that can't be loaded throws instead of being skipped", (t) => {
+    t.throws(() => transformSync(`const a = 1`, {plugins: [{path: "/does/not/exist.wasm", config: {}}]}));
+});
+
+test("ast hook - the module can be rewritten from JS", (t) => {
+    const output = transformSync(`const a: number = 1`, {}, (module) => {
+        t.is(module.type, "Module");
+        const init = module.body[0].declarations[0].init;
+        init.value = 2;
+        init.raw = "2";
+        return module;
+    });
+    t.is(output.code, "var a = 2;\n");
+});
+
+test("ast hook - returning nothing keeps the module", (t) => {
+    let stage;
+    const output = transformSync(`import {foo} from "bar"`, {astHookStage: "parse"}, (module) => {
+        stage = module.body[0].type;
+    });
+    t.is(stage, "ImportDeclaration");
+    t.is(output.code, transformSync(`import {foo} from "bar"`).code);
+});
+
+test("ast hook - unknown stages throw", (t) => {
+    t.throws(() => transformSync(`1`, {astHookStage: "nope"}, (m) => m), {message: /nope/});
+});
+
+test("explain transform - code after every pass", (t) => {
+    const source = `import {foo} from "bar";\nconst a: number = await foo;`;
+    const explained = explainTransform(source);
+    console.log("explained", explained.stages.map((s) => [s.name, s.changed, s.durationMs]));
+    t.deepEqual(explained.output, transformSync(source));
+    t.deepEqual(explained.stages.map((s) => s.name), [
+        "parse",
+        "inlineConstEnums",
+        "exportStarHelper",
+        "importRewrite",
+        "decorators",
+        "stripTypes",
+        "react",
+        "esmExports",
+        "commonJs",
+        "injectHelpers",
+        "allToVar",
+        "exportsConfigurable",
+        "topLevelAwait",
+    ]);
+    const stage = (name) => explained.stages.find((s) => s.name === name);
+    t.true(stage("importRewrite").changed);
+    t.true(stage("importRewrite").code.includes(`require("bar")`));
+    t.false(stage("decorators").changed);
+    t.true(stage("topLevelAwait").code.includes("async ()=>"));
+    t.true(explained.stages.every((s) => s.durationMs >= 0 && s.ast === undefined));
+});
+
+test("explain transform - includes the ast when asked", (t) => {
+    const explained = explainTransform(`1`, {}, true);
+    t.is(JSON.parse(explained.stages[0].ast).type, "Module");
+});
+
+test.serial("logger - receives records at or above its level", async (t) => {
+    const records = [];
+    setLogger((level, target, message) => records.push({level, target, message}), "debug");
+    try {
+        transformSync(`1`);
+        await new Promise((resolve) => setTimeout(resolve, 50));
+    } finally {
+        clearLogger();
+    }
+    const record = records.find((r) => r.message === "running pass stripTypes");
+    t.is(record.level, "debug");
+    t.is(record.target, "swc_ts_repl_transpile::pipeline");
+});
+
+test.serial("logger - filters by level and stops when cleared", async (t) => {
+    const records = [];
+    setLogger((level) => records.push(level), "warn");
+    transformSync(`1`);
+    clearLogger();
+    transformSync(`1`);
+    await new Promise((resolve) => setTimeout(resolve, 50));
+    t.deepEqual(records, []);
+});
+
+test("logger - rejects unknown levels", (t) => {
+    t.throws(() => setLogger(() => {}, "loud"), {message: /Unknown log level/});
+});
+
+test("top level await - declarations without an initializer", async (t) => {
+    const {code} = transformSync(`let x;\nawait Promise.resolve(1);`);
+    const ctx = vm.createContext({exports: {}, Promise});
+    t.is(await vm.runInContext(code, ctx), 1);
+    t.is(vm.runInContext("x", ctx), undefined);
+});
+
+test("should rewrite imports with string names", (t) => {
+    const {code} = transformSync(`import {"a-b" as ab} from "m"; ab`);
+    const ctx = vm.createContext({exports: {}, require: () => ({"a-b": 42})});
+    t.is(vm.runInContext(code, ctx), 42);
+});
@@ -1,6 +1,8 @@
+#[cfg(feature = "napi")]
 extern crate napi_build;
 
 fn main() {
     // println!("OK")
+  #[cfg(feature = "napi")]
   napi_build::setup();
 }
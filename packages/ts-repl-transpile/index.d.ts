@@ -3,28 +3,466 @@
 
 /* auto-generated by NAPI-RS */
 
+/**
+ * Options shared by every entry point. Anything left unset falls back to what
+ * the REPL has always done: parse decorated TypeScript, target es2016 and
+ * lower to non-strict CommonJS.
+ */
+export interface TransformOptions {
+  /**
+   * Path of the file the source comes from. Named in diagnostics and source
+   * maps, and decides `syntax` when that is not set.
+   */
+  filename?: string
+  /**
+   * "typescript", "tsx", "ecmascript" or "jsx". Defaults to what the
+   * extension of `filename` implies, or "typescript".
+   */
+  syntax?: string
+  /**
+   * "preserve" (default) parses decorators and leaves them in the output,
+   * "legacy" lowers them like TypeScript's experimentalDecorators,
+   * "proposal" lowers them per the stage 2 proposal, "none" rejects them
+   */
+  decorators?: string
+  /** Emit design:type metadata when lowering legacy decorators */
+  decoratorMetadata?: boolean
+  /** "es3" .. "es2022" or "esnext", defaults to "es2016" */
+  target?: string
+  /** Emit "use strict" at the top of the output (default false) */
+  strictMode?: boolean
+  module?: ModuleOptions
+  /** How JSX is lowered when `syntax` is "tsx" or "jsx" */
+  jsx?: JsxOptions
+  /**
+   * The pass `transformSync`'s `astHook` runs after, "stripTypes" by
+   * default. "parse" runs it on the module as parsed.
+   */
+  astHookStage?: string
+  /**
+   * swc Wasm plugins run on the parsed source before the REPL passes, in
+   * order. Needs the `plugin` cargo feature.
+   */
+  plugins?: Array<PluginOptions>
+  /** Return a source map in `map` (default false) */
+  sourceMaps?: boolean
+  /**
+   * 0-based line of the document the evaluated snippet starts on, so the
+   * source map points into the document rather than the snippet
+   */
+  lineOffset?: number
+  /** 0-based column the snippet's first line starts at */
+  columnOffset?: number
+  /**
+   * How `evaluableSpans` reads its target and reports positions: "bytes"
+   * (0-based UTF-8 offsets), "utf16" (0-based UTF-16 offsets, like
+   * `TextDocument.offsetAt`) or "lineColumn" (UTF-16 offsets, plus each
+   * span's `range`). Unset, they are swc's byte positions, which start at 1.
+   */
+  offsetEncoding?: string
+}
+export interface JsxOptions {
+  /**
+   * "classic" (default) calls `pragma`, "automatic" imports from
+   * `importSource`/jsx-runtime, which is then required like any import
+   */
+  runtime?: string
+  /** Classic runtime only, defaults to "React.createElement" */
+  pragma?: string
+  /** Classic runtime only, defaults to "React.Fragment" */
+  pragmaFrag?: string
+  /** Automatic runtime only, defaults to "react" */
+  importSource?: string
+  /** Use the development runtime (jsxDEV) with source locations */
+  development?: boolean
+}
+export interface PluginOptions {
+  /** Path to the plugin's .wasm file */
+  path: string
+  /**
+   * Passed to the plugin as its config, like the second element of an
+   * entry in swc's `jsc.experimental.plugins`
+   */
+  config?: any
+}
+export interface ModuleOptions {
+  /**
+   * "commonjs" (default) lowers to `require`/`exports` for `vm.Script`.
+   * "es" keeps `import`/`export` for `vm.SourceTextModule`, exporting every
+   * top level binding so later snippets can import it; the other module
+   * options don't apply to it.
+   */
+  type?: string
+  /** Define `__esModule` as non-enumerable instead of via defineProperty */
+  strict?: boolean
+  /** "swc" (default), "babel", "node" or "none" */
+  importInterop?: string
+  /** Leave dynamic `import()` untouched */
+  ignoreDynamic?: boolean
+  /** Keep top level `this` instead of rewriting it to undefined */
+  allowTopLevelThis?: boolean
+}
+/**
+ * A location in the evaluated source. Offsets are 0-based bytes, lines are
+ * 1-based and columns are 0-based characters.
+ */
+export interface DiagnosticSpan {
+  start: number
+  end: number
+  startLine: number
+  startColumn: number
+  endLine: number
+  endColumn: number
+  label?: string
+}
+export interface Diagnostic {
+  message: string
+  /** "error", "warning", "note" or "help" */
+  severity: string
+  code?: string
+  span?: DiagnosticSpan
+  /** Secondary labels and notes attached to the diagnostic */
+  related: Array<DiagnosticSpan>
+}
+/**
+ * What kind of node a `Neighbor` is. The names follow the ESTree ones where
+ * there is one.
+ */
+export const enum NodeKind {
+  Statement = 'Statement',
+  ExpressionStatement = 'ExpressionStatement',
+  ReturnStatement = 'ReturnStatement',
+  BlockStatement = 'BlockStatement',
+  WithStatement = 'WithStatement',
+  LabeledStatement = 'LabeledStatement',
+  IfStatement = 'IfStatement',
+  SwitchStatement = 'SwitchStatement',
+  SwitchCase = 'SwitchCase',
+  ThrowStatement = 'ThrowStatement',
+  TryStatement = 'TryStatement',
+  WhileStatement = 'WhileStatement',
+  DoWhileStatement = 'DoWhileStatement',
+  ForStatement = 'ForStatement',
+  ForInStatement = 'ForInStatement',
+  ForOfStatement = 'ForOfStatement',
+  AwaitExpression = 'AwaitExpression',
+  ArrayLiteral = 'ArrayLiteral',
+  ArrowFunctionExpression = 'ArrowFunctionExpression',
+  AssignmentExpression = 'AssignmentExpression',
+  BinaryExpression = 'BinaryExpression',
+  MemberExpression = 'MemberExpression',
+  SuperPropExpression = 'SuperPropExpression',
+  ConditionalExpression = 'ConditionalExpression',
+  CallExpression = 'CallExpression',
+  OptionalCallExpression = 'OptionalCallExpression',
+  NewExpression = 'NewExpression',
+  SequenceExpression = 'SequenceExpression',
+  Identifier = 'Identifier',
+  ClassExpression = 'ClassExpression',
+  YieldExpression = 'YieldExpression',
+  FunctionExpression = 'FunctionExpression',
+  Function = 'Function',
+  UpdateExpression = 'UpdateExpression',
+  MetaProperty = 'MetaProperty',
+  ParenthesisExpression = 'ParenthesisExpression',
+  OptionalChainingExpression = 'OptionalChainingExpression',
+  TaggedTemplateExpression = 'TaggedTemplateExpression',
+  ThisExpression = 'ThisExpression',
+  UnaryExpression = 'UnaryExpression',
+  ObjectExpression = 'ObjectExpression',
+  StringLiteral = 'StringLiteral',
+  BooleanLiteral = 'BooleanLiteral',
+  NullLiteral = 'NullLiteral',
+  NumericLiteral = 'NumericLiteral',
+  BigIntLiteral = 'BigIntLiteral',
+  RegExpLiteral = 'RegExpLiteral',
+  TemplateLiteral = 'TemplateLiteral',
+  ClassMethod = 'ClassMethod',
+  ClassMember = 'ClassMember',
+  ClassProp = 'ClassProp',
+  ComputedPropertyName = 'ComputedPropertyName',
+  Constructor = 'Constructor',
+  Decorator = 'Decorator',
+  VariableDeclaration = 'VariableDeclaration',
+  VariableDeclarator = 'VariableDeclarator',
+  ClassDeclaration = 'ClassDeclaration',
+  FunctionDeclaration = 'FunctionDeclaration',
+  Parameter = 'Parameter',
+  TsEnumDeclaration = 'TsEnumDeclaration',
+  TsEnumMember = 'TsEnumMember',
+  ArrayPattern = 'ArrayPattern',
+  RestElement = 'RestElement',
+  ObjectPattern = 'ObjectPattern',
+  AssignPattern = 'AssignPattern',
+  KeyValueProperty = 'KeyValueProperty',
+  KeyValuePatternProperty = 'KeyValuePatternProperty',
+  ModuleDeclaration = 'ModuleDeclaration',
+  Import = 'Import',
+  ImportDeclaration = 'ImportDeclaration',
+  ImportNamespaceSpecifier = 'ImportNamespaceSpecifier',
+  ImportSpecifier = 'ImportSpecifier',
+  ImportDefaultSpecifier = 'ImportDefaultSpecifier',
+  ExportDeclaration = 'ExportDeclaration',
+  ExportAllDeclaration = 'ExportAllDeclaration',
+  ExportDefaultDeclaration = 'ExportDefaultDeclaration',
+  ExportSpecifier = 'ExportSpecifier',
+  ExportDefaultSpecifier = 'ExportDefaultSpecifier',
+  ExportNamespaceSpecifier = 'ExportNamespaceSpecifier',
+  ExportNamedDeclaration = 'ExportNamedDeclaration',
+  JSXElement = 'JSXElement',
+  JSXFragment = 'JSXFragment',
+  JSXAttribute = 'JSXAttribute',
+  JSXExpressionContainer = 'JSXExpressionContainer',
+  JSXMemberExpression = 'JSXMemberExpression'
+}
+/**
+ * A place in a document, like VS Code's `Position`. Both are 0-based and
+ * `character` counts UTF-16 code units.
+ */
+export interface Position {
+  line: number
+  character: number
+}
+export interface Range {
+  start: Position
+  end: Position
+}
+/**
+ * A node around the target. `parent` is the index of the innermost other
+ * span that contains this one, and `depth` how many spans do.
+ */
 export interface Neighbor {
   start: number
   end: number
-  type: string
+  type: NodeKind
+  parent?: number
+  depth: number
+  /** The binding this declares, for declarations of a single one */
+  name?: string
+  /** The node's source on one line, cut to 40 chars */
+  preview: string
+  /**
+   * Where the node is as lines and characters, when the `offsetEncoding`
+   * is "lineColumn"
+   */
+  range?: Range
 }
 export interface TransformOutput {
   code: string
   isAsync: boolean
   map?: string
+  /**
+   * Parse and transform errors, absent when there is nothing to report.
+   * `code` is empty when the source could not be parsed.
+   */
+  diagnostics?: Array<Diagnostic>
+}
+export interface TransformStage {
+  /** "parse", "plugins" or the name of a pass */
+  name: string
+  /** The module after this stage, emitted without comments */
+  code: string
+  /** swc's JSON AST after this stage, when asked for */
+  ast?: string
+  /** Time spent in the stage, not counting the emit for `code` */
+  durationMs: number
+  /** Whether `code` differs from the stage before */
+  changed: boolean
+}
+export interface ExplainedTransform {
+  stages: Array<TransformStage>
+  /** What `transformSync` returns for the same source and options */
+  output: TransformOutput
+  totalMs: number
 }
 export interface TransformOutputRegular {
   code: string
   map?: string
+  diagnostics?: Array<Diagnostic>
 }
 export interface EvaluableSpans {
   spans: Array<Neighbor>
+  diagnostics?: Array<Diagnostic>
 }
-export function evaluableSpans(source: string, target: number): EvaluableSpans
+/**
+ * The forms around a selection. `spans` is what `evaluableSpans` returns
+ * for its first character, or the character before a cursor, and the rest
+ * are indexes into it.
+ */
+export interface SelectedForms {
+  spans: Array<Neighbor>
+  /**
+   * The smallest form that holds more than the selection. For a cursor,
+   * the innermost form before it.
+   */
+  expand?: number
+  /**
+   * The largest form inside the selection that is smaller than it, which
+   * starts where it does. Not set for a cursor.
+   */
+  shrink?: number
+  /**
+   * The statement or declaration at the top of the module that holds the
+   * selection
+   */
+  topLevel?: number
+  /** The innermost expression that holds the selection */
+  innermostExpression?: number
+  diagnostics?: Array<Diagnostic>
+}
+/** The forms to evaluate for one selection */
+export interface CoveringForms {
+  /**
+   * The fewest complete forms that cover the selection, in order, without
+   * `parent`s. For a cursor, the innermost form before it.
+   */
+  forms: Array<Neighbor>
+  /**
+   * Whether the selection starts or ends inside one of `forms`, so that
+   * evaluating it as it is would cut through a form
+   */
+  cutsForm: boolean
+}
+export interface EvaluableSelections {
+  /** The forms for each selection, in the order they were given */
+  selections: Array<CoveringForms>
+  diagnostics?: Array<Diagnostic>
+}
+/** Same as `transformSync`, without blocking the calling thread */
+export function transform(source: string, options?: TransformOptions | undefined | null): Promise<TransformOutput>
+/** Same as `evaluableSpans`, without blocking the calling thread */
+export function evaluableSpansAsync(source: string, target: number | Position, options?: TransformOptions | undefined | null): Promise<EvaluableSpans>
+/** One `at ...` line of a V8 stack. Lines and columns are 1-based like V8's. */
+export interface StackFrame {
+  functionName?: string
+  file: string
+  line: number
+  column: number
+  /** Whether the position was translated through the source map */
+  mapped: boolean
+}
+export interface MappedStackTrace {
+  /** The stack with every frame from the evaluated code rewritten */
+  stack: string
+  frames: Array<StackFrame>
+}
+export function evaluableSpans(source: string, target: number | Position, options?: TransformOptions | undefined | null): EvaluableSpans
+/**
+ * The forms to expand or shrink the selection from `start` to `end` to, and
+ * the top-level form and innermost expression holding it. Without `end` it
+ * is a cursor at `start`.
+ */
+export function selectForms(source: string, start: number | Position, end?: number | Position | undefined | null, options?: TransformOptions | undefined | null): SelectedForms
+/** A selection from `start` to `end`, as offsets or positions */
+export interface SelectionRange {
+  start: number | Position
+  end: number | Position
+}
+/**
+ * For each selection, like the ones of a multi-cursor editor, the complete
+ * forms that cover it, and whether it cuts through one
+ */
+export function evaluableSelections(source: string, selections: Array<SelectionRange>, options?: TransformOptions | undefined | null): EvaluableSelections
 /**
  * Performs a transformation on the source string such that its output
  * is suitable for usage in a REPL environment.
+ *
+ * `astHook` is called with the module as swc's JSON AST after the pass
+ * named by `options.astHookStage`, and can return a replacement.
  */
-export function transformSync(source: string): TransformOutput
+export function transformSync(source: string, options?: TransformOptions | undefined | null, astHook?: (module: any) => any): TransformOutput
+/**
+ * Runs `transformSync`'s passes one at a time, returning the code after
+ * each of them, with the AST too when `includeAst` is set
+ */
+export function explainTransform(source: string, options?: TransformOptions | undefined | null, includeAst?: boolean | undefined | null): ExplainedTransform
 /** Translates TS to JS */
-export function transformSyncRegular(source: string): TransformOutputRegular
+export function transformSyncRegular(source: string, options?: TransformOptions | undefined | null): TransformOutputRegular
+/**
+ * Rewrites a stack produced by running a transform's output with
+ * `vm.runInContext(code, ctx, { filename })` so frames from that evaluation
+ * point at the original TypeScript. `map` is the transform's `map`, made
+ * with `lineOffset`/`columnOffset` for positions in the whole document.
+ */
+export function mapStackTrace(stack: string, map: string, filename: string): MappedStackTrace
+/**
+ * Calls `callback` with every `log` record from the transpiler at `level`
+ * (default `info`) or more severe, replacing any earlier callback. Records
+ * from other threads are delivered on the JS thread.
+ */
+export function setLogger(callback: (level: string, target: string, message: string) => void, level?: 'off' | 'error' | 'warn' | 'info' | 'debug' | 'trace' | undefined | null): void
+/** Stops calling the callback given to `setLogger` */
+export function clearLogger(): void
+/** Source the session transformed for a namespace, in the order it did so */
+export interface Evaluation {
+  source: string
+  /** Top level bindings the source declared */
+  declared: Array<string>
+}
+export interface SessionTransformOutput {
+  code: string
+  isAsync: boolean
+  map?: string
+  diagnostics?: Array<Diagnostic>
+  /** Top level bindings the code declares */
+  declared: Array<string>
+  /**
+   * The subset of `declared` an earlier evaluation in the namespace had
+   * already declared
+   */
+  redeclared: Array<string>
+}
+export interface ConstEnumMember {
+  name: string
+  /** A number or a string */
+  value: any
+}
+export interface ConstEnum {
+  name: string
+  members: Array<ConstEnumMember>
+}
+/**
+ * Remembers what each namespace of a REPL has evaluated so later transforms
+ * in the same namespace can build on it
+ */
+export class ReplSession {
+  constructor()
+  /**
+   * Sets the options used by transforms in `namespace` that don't pass
+   * their own
+   */
+  setOptions(namespace: string, options: TransformOptions): void
+  /**
+   * Same as `transformSync`, for code that will be evaluated in
+   * `namespace`. What the code declares is only recorded when it transforms
+   * without errors.
+   */
+  transform(namespace: string, source: string, options?: TransformOptions | undefined | null): SessionTransformOutput
+  /** Top level bindings declared in `namespace` so far */
+  bindings(namespace: string): Array<string>
+  constEnums(namespace: string): Array<ConstEnum>
+  /** What was evaluated in `namespace`, oldest first */
+  history(namespace: string): Array<Evaluation>
+  /** Helpers already defined in `namespace` */
+  helpers(namespace: string): Array<string>
+  namespaces(): Array<string>
+  /** Forgets a binding, for when it is removed from the namespace's context */
+  unmap(namespace: string, binding: string): void
+  /**
+   * Forgets everything about `namespace`, or every namespace when none is
+   * given
+   */
+  reset(namespace?: string | undefined | null): void
+  /** Serializes every namespace's state, for `fromJson` */
+  toJson(): string
+  /**
+   * Restores a session serialized by `toJson`, for fresh contexts: each
+   * namespace keeps its options and history, but not its bindings, helpers
+   * or const enums
+   */
+  static fromJson(json: string): ReplSession
+  /** Writes the session to `path`, creating its directory if needed */
+  save(path: string): void
+  /** Reads a session written by `save`, like `fromJson` */
+  static load(path: string): ReplSession
+}
@@ -246,8 +246,18 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`)
 }
 
-const { evaluableSpans, transformSync, transformSyncRegular } = nativeBinding
+const { NodeKind, ReplSession, transform, evaluableSpansAsync, evaluableSpans, selectForms, evaluableSelections, transformSync, explainTransform, transformSyncRegular, mapStackTrace, setLogger, clearLogger } = nativeBinding
 
+module.exports.NodeKind = NodeKind
+module.exports.ReplSession = ReplSession
+module.exports.transform = transform
+module.exports.evaluableSpansAsync = evaluableSpansAsync
 module.exports.evaluableSpans = evaluableSpans
+module.exports.selectForms = selectForms
+module.exports.evaluableSelections = evaluableSelections
 module.exports.transformSync = transformSync
+module.exports.explainTransform = explainTransform
 module.exports.transformSyncRegular = transformSyncRegular
+module.exports.mapStackTrace = mapStackTrace
+module.exports.setLogger = setLogger
+module.exports.clearLogger = clearLogger
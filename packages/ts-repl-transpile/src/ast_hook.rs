@@ -0,0 +1,71 @@
+use crate::error::{Error, Result};
+use crate::pipeline::{PassContext, Pipeline, ReplPass};
+use serde::Deserialize;
+use swc_core::ecma::ast::Module;
+
+// Lets code that isn't Rust rewrite the module. The module goes out as swc's
+// JSON AST (the shape `@swc/core`'s `parse` returns) and whatever comes back
+// replaces it. Deep ASTs are fine, the recursion limit is off when reading.
+
+type Callback = dyn Fn(String) -> Result<Option<String>> + Send + Sync;
+
+/// "astHook": calls back with the module as JSON. Returning `None` keeps the
+/// module as it was.
+pub struct AstHook {
+  callback: Box<Callback>,
+}
+
+impl AstHook {
+  pub fn new(callback: impl Fn(String) -> Result<Option<String>> + Send + Sync + 'static) -> Self {
+    AstHook {
+      callback: Box::new(callback),
+    }
+  }
+}
+
+impl ReplPass for AstHook {
+  fn name(&self) -> &str {
+    "astHook"
+  }
+
+  fn run(&self, module: Module, _ctx: &mut PassContext) -> Result<Module> {
+    let json = serde_json::to_string(&module)
+      .map_err(|err| Error::failure(format!("Could not serialize the module: {}", err)))?;
+    match (self.callback)(json)? {
+      Some(json) => module_from_json(&json),
+      None => Ok(module),
+    }
+  }
+}
+
+pub fn module_from_json(json: &str) -> Result<Module> {
+  let invalid = |err: serde_json::Error| {
+    Error::invalid_arg(format!("astHook returned an invalid module: {}", err))
+  };
+  let mut de = serde_json::Deserializer::from_str(json);
+  de.disable_recursion_limit();
+  let module = Module::deserialize(&mut de).map_err(invalid)?;
+  de.end().map_err(invalid)?;
+  Ok(module)
+}
+
+/// The stage the hook runs at when none is given
+pub const DEFAULT_STAGE: &str = "stripTypes";
+
+/// Adds `hook` to `pipeline` right after the pass called `stage`. "parse"
+/// runs it before any pass, on the module as parsed.
+pub fn insert(pipeline: &mut Pipeline, stage: &str, hook: AstHook) -> Result<()> {
+  if stage != "parse" {
+    pipeline.insert_after(stage, hook)?;
+    return Ok(());
+  }
+  match pipeline.names().first().map(|name| name.to_string()) {
+    Some(first) => {
+      pipeline.insert_before(&first, hook)?;
+    }
+    None => {
+      pipeline.push(hook);
+    }
+  }
+  Ok(())
+}
@@ -0,0 +1,112 @@
+use crate::bindings::{input_location, js_error};
+use crate::options::TransformOptions;
+use crate::{Error, EvaluableSpans, Position, Target, TransformOutput};
+use napi::bindgen_prelude::AsyncTask;
+use napi::{Either, Env, Task};
+use std::mem;
+
+// Promise versions of the sync entry points. `compute` runs on the libuv
+// thread pool so large documents don't block the extension host. Errors are
+// kept until `reject`, which is on the JS thread and can make the JS error.
+
+pub struct TransformTask {
+  source: String,
+  options: Option<TransformOptions>,
+  input: String,
+  error: Option<Error>,
+}
+
+#[napi]
+impl Task for TransformTask {
+  type Output = TransformOutput;
+  type JsValue = TransformOutput;
+
+  fn compute(&mut self) -> napi::Result<Self::Output> {
+    let (source, options) = (mem::take(&mut self.source), self.options.take());
+    crate::panic::catch(
+      || "transform".into(),
+      || crate::transform_sync(source, options),
+    )
+    .map_err(|err| {
+      self.error = Some(err.clone());
+      err.into()
+    })
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> napi::Result<Self::JsValue> {
+    Ok(output)
+  }
+
+  fn reject(&mut self, env: Env, err: napi::Error) -> napi::Result<Self::JsValue> {
+    match self.error.take() {
+      Some(error) => Err(js_error(env, error, || mem::take(&mut self.input))),
+      None => Err(err),
+    }
+  }
+}
+
+pub struct EvaluableSpansTask {
+  source: String,
+  target: Target,
+  options: Option<TransformOptions>,
+  input: String,
+  error: Option<Error>,
+}
+
+#[napi]
+impl Task for EvaluableSpansTask {
+  type Output = EvaluableSpans;
+  type JsValue = EvaluableSpans;
+
+  fn compute(&mut self) -> napi::Result<Self::Output> {
+    let (source, options) = (mem::take(&mut self.source), self.options.take());
+    crate::panic::catch(
+      || "evaluableSpansAsync".into(),
+      || crate::evaluable_spans(source, self.target, options),
+    )
+    .map_err(|err| {
+      self.error = Some(err.clone());
+      err.into()
+    })
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> napi::Result<Self::JsValue> {
+    Ok(output)
+  }
+
+  fn reject(&mut self, env: Env, err: napi::Error) -> napi::Result<Self::JsValue> {
+    match self.error.take() {
+      Some(error) => Err(js_error(env, error, || mem::take(&mut self.input))),
+      None => Err(err),
+    }
+  }
+}
+
+/// Same as `transformSync`, without blocking the calling thread
+#[napi]
+pub fn transform(source: String, options: Option<TransformOptions>) -> AsyncTask<TransformTask> {
+  AsyncTask::new(TransformTask {
+    source,
+    input: input_location(&options),
+    options,
+    error: None,
+  })
+}
+
+/// Same as `evaluableSpans`, without blocking the calling thread
+#[napi(
+  ts_args_type = "source: string, target: number | Position, options?: TransformOptions | undefined | null"
+)]
+pub fn evaluable_spans_async(
+  source: String,
+  target: Either<u32, Position>,
+  options: Option<TransformOptions>,
+) -> AsyncTask<EvaluableSpansTask> {
+  AsyncTask::new(EvaluableSpansTask {
+    source,
+    target: crate::bindings::target(target),
+    input: input_location(&options),
+    options,
+    error: None,
+  })
+}
@@ -0,0 +1,214 @@
+//! Runs the transpiler outside of node, printing what the napi functions
+//! would return as JSON.
+//!
+//! ```text
+//! ts-repl-transpile repl [--options JSON] [FILE]
+//! ts-repl-transpile regular [--options JSON] [FILE]
+//! ts-repl-transpile spans (OFFSET | LINE:CHARACTER) [--options JSON] [FILE]
+//! ts-repl-transpile forms TARGET[..TARGET] [--options JSON] [FILE]
+//! ts-repl-transpile cover TARGET[..TARGET][,...] [--options JSON] [FILE]
+//! ts-repl-transpile explain [--ast] [--options JSON] [FILE]
+//! ```
+//!
+//! Source is read from FILE, or stdin when it is missing or `-`. Exits with 1
+//! when the transform reports errors, so it can be used in scripts, and 2 on
+//! invalid arguments. Needs the `cli` feature. `spans`
+//! takes an offset, or a 0-based line and UTF-16 character, `forms` a cursor
+//! or selection of those, and `cover` a list of cursors and selections.
+
+use serde::Serialize;
+use std::io::Read;
+use std::process::exit;
+use std::{env, fs, io};
+use swc_ts_repl_transpile::{
+  evaluable_selections, evaluable_spans, explain_transform, select_forms, transform_sync,
+  transform_sync_regular, Diagnostic, Position, Selection, Target, TransformOptions,
+};
+
+const USAGE: &str =
+  "usage: ts-repl-transpile (repl | regular | spans (OFFSET | LINE:CHARACTER) | forms TARGET[..TARGET] | cover TARGET[..TARGET][,...] | explain [--ast]) [--options JSON] [FILE]";
+
+enum Command {
+  Repl,
+  Regular,
+  Spans(Target),
+  Forms(Selection),
+  Cover(Vec<Selection>),
+  Explain { ast: bool },
+}
+
+struct Args {
+  command: Command,
+  options: Option<TransformOptions>,
+  file: Option<String>,
+}
+
+fn parse_target(target: &str) -> Option<Target> {
+  match target.split_once(':') {
+    Some((line, character)) => Some(Target::Position(Position {
+      line: line.parse().ok()?,
+      character: character.parse().ok()?,
+    })),
+    None => target.parse().ok().map(Target::Offset),
+  }
+}
+
+fn parse_selection(selection: &str) -> Option<Selection> {
+  match selection.split_once("..") {
+    Some((start, end)) => Some((parse_target(start)?, parse_target(end)?).into()),
+    None => parse_target(selection).map(Selection::from),
+  }
+}
+
+fn is_help(arg: &str) -> bool {
+  arg == "-h" || arg == "--help"
+}
+
+/// The arguments, or `None` when help was asked for
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+  let mut command = match args.next().as_deref() {
+    Some(arg) if is_help(arg) => return Ok(None),
+    Some("repl") => Command::Repl,
+    Some("regular") => Command::Regular,
+    Some("spans") => {
+      let target = args
+        .next()
+        .ok_or("spans needs an OFFSET or LINE:CHARACTER")?;
+      Command::Spans(parse_target(&target).ok_or_else(|| format!("invalid target: {}", target))?)
+    }
+    Some("forms") => {
+      let selection = args
+        .next()
+        .ok_or("forms needs a TARGET or TARGET..TARGET")?;
+      Command::Forms(
+        parse_selection(&selection).ok_or_else(|| format!("invalid selection: {}", selection))?,
+      )
+    }
+    Some("cover") => {
+      let selections = args.next().ok_or("cover needs a list of selections")?;
+      Command::Cover(
+        selections
+          .split(',')
+          .map(|selection| {
+            parse_selection(selection).ok_or_else(|| format!("invalid selection: {}", selection))
+          })
+          .collect::<Result<_, _>>()?,
+      )
+    }
+    Some("explain") => Command::Explain { ast: false },
+    Some(other) => return Err(format!("unknown command: {}", other)),
+    None => return Err("missing command".into()),
+  };
+
+  let mut options = None;
+  let mut file = None;
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      arg if is_help(arg) => return Ok(None),
+      "--ast" => match &mut command {
+        Command::Explain { ast } => *ast = true,
+        _ => return Err("--ast only applies to explain".into()),
+      },
+      "--options" => {
+        let json = args.next().ok_or("--options needs a JSON value")?;
+        options =
+          Some(serde_json::from_str(&json).map_err(|err| format!("invalid --options: {}", err))?);
+      }
+      _ if file.is_none() => file = Some(arg),
+      _ => return Err(format!("unexpected argument: {}", arg)),
+    }
+  }
+
+  Ok(Some(Args {
+    command,
+    options,
+    file,
+  }))
+}
+
+fn read_source(file: Option<&str>) -> io::Result<String> {
+  match file {
+    None | Some("-") => {
+      let mut source = String::new();
+      io::stdin().read_to_string(&mut source)?;
+      Ok(source)
+    }
+    Some(path) => fs::read_to_string(path),
+  }
+}
+
+fn has_errors(diagnostics: &Option<Vec<Diagnostic>>) -> bool {
+  diagnostics
+    .as_ref()
+    .is_some_and(|d| d.iter().any(Diagnostic::is_error))
+}
+
+fn print<T: Serialize>(output: &T) {
+  println!(
+    "{}",
+    serde_json::to_string_pretty(output).expect("outputs serialize to JSON")
+  );
+}
+
+fn run(args: Args) -> Result<bool, String> {
+  let source = read_source(args.file.as_deref()).map_err(|err| err.to_string())?;
+  let failed = match args.command {
+    Command::Repl => {
+      let output = transform_sync(source, args.options).map_err(|err| err.to_string())?;
+      print(&output);
+      has_errors(&output.diagnostics)
+    }
+    Command::Regular => {
+      let output = transform_sync_regular(source, args.options).map_err(|err| err.to_string())?;
+      print(&output);
+      has_errors(&output.diagnostics)
+    }
+    Command::Spans(target) => {
+      let output = evaluable_spans(source, target, args.options).map_err(|err| err.to_string())?;
+      print(&output);
+      has_errors(&output.diagnostics)
+    }
+    Command::Forms(selection) => {
+      let output = select_forms(source, selection, args.options).map_err(|err| err.to_string())?;
+      print(&output);
+      has_errors(&output.diagnostics)
+    }
+    Command::Cover(selections) => {
+      let output =
+        evaluable_selections(source, selections, args.options).map_err(|err| err.to_string())?;
+      print(&output);
+      has_errors(&output.diagnostics)
+    }
+    Command::Explain { ast } => {
+      let output = explain_transform(source, args.options, ast).map_err(|err| err.to_string())?;
+      print(&output);
+      has_errors(&output.output.diagnostics)
+    }
+  };
+  Ok(failed)
+}
+
+fn main() {
+  env_logger::init();
+
+  let args = match parse_args(env::args().skip(1)) {
+    Ok(Some(args)) => args,
+    Ok(None) => {
+      println!("{}", USAGE);
+      return;
+    }
+    Err(message) => {
+      eprintln!("{}\n{}", message, USAGE);
+      exit(2);
+    }
+  };
+
+  match run(args) {
+    Ok(false) => {}
+    Ok(true) => exit(1),
+    Err(message) => {
+      eprintln!("{}", message);
+      exit(2);
+    }
+  }
+}
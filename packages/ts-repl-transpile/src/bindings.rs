@@ -0,0 +1,479 @@
+use crate::ast_hook::{self, AstHook};
+use crate::explain::ExplainedTransform;
+use crate::forms::{EvaluableSelections, SelectedForms, Selection};
+use crate::session::{ConstEnum, Evaluation, ReplSession, SessionTransformOutput};
+use crate::stack_trace::MappedStackTrace;
+use crate::{
+  Error, ErrorKind, EvaluableSpans, Pipeline, Position, Target, TransformOptions, TransformOutput,
+  TransformOutputRegular,
+};
+use napi::threadsafe_function::{
+  ErrorStrategy, ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode,
+};
+use napi::{Either, Env, JsFunction, JsObject, ValueType};
+use std::thread::{self, ThreadId};
+
+// The node API. Everything here forwards to the Rust API of the same name,
+// through `guard` so a panic throws instead of taking down the process.
+
+/// Where in its document the source given to a call starts
+pub(crate) fn input_location(options: &Option<TransformOptions>) -> String {
+  let options = options.as_ref();
+  format!(
+    "{}:{}:{}",
+    options
+      .and_then(|options| options.filename.as_deref())
+      .unwrap_or("<anonymous>"),
+    options.and_then(|options| options.line_offset).unwrap_or(0) + 1,
+    options
+      .and_then(|options| options.column_offset)
+      .unwrap_or(0)
+      + 1,
+  )
+}
+
+/// Makes the JS error for `err`. `Internal` errors get `name` and `code`
+/// `InternalError`, and `input` saying what the call was working on.
+pub(crate) fn js_error(env: Env, err: Error, input: impl FnOnce() -> String) -> napi::Error {
+  if err.kind != ErrorKind::Internal {
+    return err.into();
+  }
+  let internal = || -> napi::Result<napi::Error> {
+    let mut error = env.create_error(napi::Error::from_reason(err.message.clone()))?;
+    error.set_named_property("name", env.create_string("InternalError")?)?;
+    error.set_named_property("code", env.create_string("InternalError")?)?;
+    error.set_named_property("input", env.create_string_from_std(input())?)?;
+    Ok(error.into_unknown().into())
+  };
+  match internal() {
+    Ok(error) => error,
+    Err(_) => err.into(),
+  }
+}
+
+/// Runs the export `name`, turning its errors and panics into JS errors
+fn guard<T>(
+  env: Env,
+  name: &str,
+  input: impl FnOnce() -> String,
+  f: impl FnOnce() -> crate::Result<T>,
+) -> napi::Result<T> {
+  crate::panic::catch(|| name.to_string(), f).map_err(|err| js_error(env, err, input))
+}
+
+/// A cursor given to `evaluableSpans` as an offset or a `Position`
+pub(crate) fn target(target: Either<u32, Position>) -> Target {
+  match target {
+    Either::A(offset) => Target::Offset(offset),
+    Either::B(position) => Target::Position(position),
+  }
+}
+
+#[napi(
+  ts_args_type = "source: string, target: number | Position, options?: TransformOptions | undefined | null"
+)]
+pub fn evaluable_spans(
+  env: Env,
+  source: String,
+  target: Either<u32, Position>,
+  options: Option<TransformOptions>,
+) -> napi::Result<EvaluableSpans> {
+  let input = input_location(&options);
+  guard(
+    env,
+    "evaluableSpans",
+    || input,
+    || crate::evaluable_spans(source, self::target(target), options),
+  )
+}
+
+/// The forms to expand or shrink the selection from `start` to `end` to, and
+/// the top-level form and innermost expression holding it. Without `end` it
+/// is a cursor at `start`.
+#[napi(
+  ts_args_type = "source: string, start: number | Position, end?: number | Position | undefined | null, options?: TransformOptions | undefined | null"
+)]
+pub fn select_forms(
+  env: Env,
+  source: String,
+  start: Either<u32, Position>,
+  end: Option<Either<u32, Position>>,
+  options: Option<TransformOptions>,
+) -> napi::Result<SelectedForms> {
+  let start = target(start);
+  let selection = Selection {
+    start,
+    end: end.map_or(start, target),
+  };
+  let input = input_location(&options);
+  guard(
+    env,
+    "selectForms",
+    || input,
+    || crate::select_forms(source, selection, options),
+  )
+}
+
+/// A selection from `start` to `end`, as offsets or positions
+#[napi(object)]
+pub struct SelectionRange {
+  pub start: Either<u32, Position>,
+  pub end: Either<u32, Position>,
+}
+
+/// For each selection, like the ones of a multi-cursor editor, the complete
+/// forms that cover it, and whether it cuts through one
+#[napi]
+pub fn evaluable_selections(
+  env: Env,
+  source: String,
+  selections: Vec<SelectionRange>,
+  options: Option<TransformOptions>,
+) -> napi::Result<EvaluableSelections> {
+  let selections = selections
+    .into_iter()
+    .map(|selection| Selection {
+      start: target(selection.start),
+      end: target(selection.end),
+    })
+    .collect();
+  let input = input_location(&options);
+  guard(
+    env,
+    "evaluableSelections",
+    || input,
+    || crate::evaluable_selections(source, selections, options),
+  )
+}
+
+/// A JS value that passes require to be `Send + Sync`, which can only be
+/// used on the thread that made it
+struct OnJsThread<F> {
+  thread: ThreadId,
+  f: F,
+}
+
+// SAFETY: `F` holds napi handles, which are only valid on the JS thread and
+// within the call that made them. `call` checks it is on that thread before
+// touching them, so a pipeline run on the libuv pool gets an error instead.
+// Dropping them elsewhere is fine, as the handles own nothing.
+unsafe impl<F> Send for OnJsThread<F> {}
+unsafe impl<F> Sync for OnJsThread<F> {}
+
+impl<F: Fn(String) -> crate::Result<Option<String>>> OnJsThread<F> {
+  fn new(f: F) -> Self {
+    OnJsThread {
+      thread: thread::current().id(),
+      f,
+    }
+  }
+
+  fn call(&self, json: String) -> crate::Result<Option<String>> {
+    if thread::current().id() != self.thread {
+      return Err(Error::failure("astHook called off the JS thread"));
+    }
+    (self.f)(json)
+  }
+}
+
+/// Calls `hook` with the module parsed from JSON and reads back what it
+/// returns, `undefined` and `null` meaning unchanged
+fn js_ast_hook(env: Env, hook: JsFunction) -> napi::Result<AstHook> {
+  let json: JsObject = env.get_global()?.get_named_property("JSON")?;
+  let parse: JsFunction = json.get_named_property("parse")?;
+  let stringify: JsFunction = json.get_named_property("stringify")?;
+  let call = move |module: String| -> napi::Result<Option<String>> {
+    let module = parse.call(None, &[env.create_string_from_std(module)?])?;
+    let returned = hook.call(None, &[module])?;
+    if matches!(returned.get_type()?, ValueType::Undefined | ValueType::Null) {
+      return Ok(None);
+    }
+    let returned = stringify.call(None, &[returned])?.coerce_to_string()?;
+    Ok(Some(returned.into_utf8()?.into_owned()?))
+  };
+  let call = OnJsThread::new(move |module: String| {
+    call(module).map_err(|err| Error::failure(format!("astHook failed: {}", err.reason)))
+  });
+  Ok(AstHook::new(move |module| call.call(module)))
+}
+
+/// Performs a transformation on the source string such that its output
+/// is suitable for usage in a REPL environment.
+///
+/// `astHook` is called with the module as swc's JSON AST after the pass
+/// named by `options.astHookStage`, and can return a replacement.
+#[napi(
+  ts_args_type = "source: string, options?: TransformOptions | undefined | null, astHook?: (module: any) => any"
+)]
+pub fn transform_sync(
+  env: Env,
+  source: String,
+  options: Option<TransformOptions>,
+  ast_hook: Option<JsFunction>,
+) -> napi::Result<TransformOutput> {
+  let input = input_location(&options);
+  guard(
+    env,
+    "transformSync",
+    || input,
+    || {
+      let hook = match ast_hook {
+        Some(hook) => hook,
+        None => return crate::transform_sync(source, options),
+      };
+      let stage = options
+        .as_ref()
+        .and_then(|options| options.ast_hook_stage.clone())
+        .unwrap_or_else(|| ast_hook::DEFAULT_STAGE.into());
+      let mut pipeline = Pipeline::default();
+      let hook =
+        js_ast_hook(env, hook).map_err(|err| Error::failure(format!("astHook: {}", err.reason)))?;
+      ast_hook::insert(&mut pipeline, &stage, hook)?;
+      crate::transform_with_pipeline(source, options, &pipeline)
+    },
+  )
+}
+
+/// Runs `transformSync`'s passes one at a time, returning the code after
+/// each of them, with the AST too when `includeAst` is set
+#[napi]
+pub fn explain_transform(
+  env: Env,
+  source: String,
+  options: Option<TransformOptions>,
+  include_ast: Option<bool>,
+) -> napi::Result<ExplainedTransform> {
+  let input = input_location(&options);
+  guard(
+    env,
+    "explainTransform",
+    || input,
+    || crate::explain_transform(source, options, include_ast.unwrap_or(false)),
+  )
+}
+
+/// Translates TS to JS
+#[napi]
+pub fn transform_sync_regular(
+  env: Env,
+  source: String,
+  options: Option<TransformOptions>,
+) -> napi::Result<TransformOutputRegular> {
+  let input = input_location(&options);
+  guard(
+    env,
+    "transformSyncRegular",
+    || input,
+    || crate::transform_sync_regular(source, options),
+  )
+}
+
+/// Rewrites a stack produced by running a transform's output with
+/// `vm.runInContext(code, ctx, { filename })` so frames from that evaluation
+/// point at the original TypeScript. `map` is the transform's `map`, made
+/// with `lineOffset`/`columnOffset` for positions in the whole document.
+#[napi]
+pub fn map_stack_trace(
+  env: Env,
+  stack: String,
+  map: String,
+  filename: String,
+) -> napi::Result<MappedStackTrace> {
+  guard(
+    env,
+    "mapStackTrace",
+    || filename.clone(),
+    || crate::map_stack_trace(&stack, &map, &filename),
+  )
+}
+
+struct LogRecord {
+  level: &'static str,
+  target: String,
+  message: String,
+}
+
+/// Calls `callback` with every `log` record from the transpiler at `level`
+/// (default `info`) or more severe, replacing any earlier callback. Records
+/// from other threads are delivered on the JS thread.
+#[napi(
+  ts_args_type = "callback: (level: string, target: string, message: string) => void, level?: 'off' | 'error' | 'warn' | 'info' | 'debug' | 'trace' | undefined | null"
+)]
+pub fn set_logger(env: Env, callback: JsFunction, level: Option<String>) -> napi::Result<()> {
+  let level = crate::logging::parse_level(level.as_deref().unwrap_or("info"))?;
+  let mut callback: ThreadsafeFunction<LogRecord, ErrorStrategy::Fatal> = callback
+    .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<LogRecord>| {
+      Ok(vec![
+        ctx.env.create_string(ctx.value.level)?,
+        ctx.env.create_string_from_std(ctx.value.target)?,
+        ctx.env.create_string_from_std(ctx.value.message)?,
+      ])
+    })?;
+  // Logging shouldn't keep node running
+  callback.unref(&env)?;
+  crate::logging::set_log_sink(level, move |record| {
+    let record = LogRecord {
+      level: match record.level() {
+        log::Level::Error => "error",
+        log::Level::Warn => "warn",
+        log::Level::Info => "info",
+        log::Level::Debug => "debug",
+        log::Level::Trace => "trace",
+      },
+      target: record.target().to_string(),
+      message: record.args().to_string(),
+    };
+    callback.call(record, ThreadsafeFunctionCallMode::NonBlocking);
+  })?;
+  Ok(())
+}
+
+/// Stops calling the callback given to `setLogger`
+#[napi]
+pub fn clear_logger() {
+  crate::logging::clear_log_sink()
+}
+
+/// Remembers what each namespace of a REPL has evaluated so later transforms
+/// in the same namespace can build on it
+#[napi(js_name = "ReplSession")]
+#[derive(Default)]
+pub struct JsReplSession {
+  inner: ReplSession,
+}
+
+#[napi]
+impl JsReplSession {
+  #[napi(constructor)]
+  pub fn new() -> Self {
+    JsReplSession {
+      inner: ReplSession::new(),
+    }
+  }
+
+  /// Sets the options used by transforms in `namespace` that don't pass
+  /// their own
+  #[napi]
+  pub fn set_options(
+    &mut self,
+    env: Env,
+    namespace: String,
+    options: TransformOptions,
+  ) -> napi::Result<()> {
+    guard(
+      env,
+      "ReplSession.setOptions",
+      || namespace.clone(),
+      || self.inner.set_options(namespace.clone(), options),
+    )
+  }
+
+  /// Same as `transformSync`, for code that will be evaluated in
+  /// `namespace`. What the code declares is only recorded when it transforms
+  /// without errors.
+  #[napi]
+  pub fn transform(
+    &mut self,
+    env: Env,
+    namespace: String,
+    source: String,
+    options: Option<TransformOptions>,
+  ) -> napi::Result<SessionTransformOutput> {
+    let input = format!("{} in {}", input_location(&options), namespace);
+    guard(
+      env,
+      "ReplSession.transform",
+      || input,
+      || self.inner.transform(namespace, source, options),
+    )
+  }
+
+  /// Top level bindings declared in `namespace` so far
+  #[napi]
+  pub fn bindings(&self, namespace: String) -> Vec<String> {
+    self.inner.bindings(namespace)
+  }
+
+  #[napi]
+  pub fn const_enums(&self, namespace: String) -> Vec<ConstEnum> {
+    self.inner.const_enums(namespace)
+  }
+
+  /// What was evaluated in `namespace`, oldest first
+  #[napi]
+  pub fn history(&self, namespace: String) -> Vec<Evaluation> {
+    self.inner.history(namespace)
+  }
+
+  /// Helpers already defined in `namespace`
+  #[napi]
+  pub fn helpers(&self, namespace: String) -> Vec<String> {
+    self.inner.helpers(namespace)
+  }
+
+  #[napi]
+  pub fn namespaces(&self) -> Vec<String> {
+    self.inner.namespaces()
+  }
+
+  /// Forgets a binding, for when it is removed from the namespace's context
+  #[napi]
+  pub fn unmap(&mut self, namespace: String, binding: String) {
+    self.inner.unmap(namespace, binding)
+  }
+
+  /// Forgets everything about `namespace`, or every namespace when none is
+  /// given
+  #[napi]
+  pub fn reset(&mut self, namespace: Option<String>) {
+    self.inner.reset(namespace)
+  }
+
+  /// Serializes every namespace's state, for `fromJson`
+  #[napi]
+  pub fn to_json(&self, env: Env) -> napi::Result<String> {
+    guard(
+      env,
+      "ReplSession.toJson",
+      || "the session".into(),
+      || self.inner.to_json(),
+    )
+  }
+
+  /// Restores a session serialized by `toJson`, for fresh contexts: each
+  /// namespace keeps its options and history, but not its bindings, helpers
+  /// or const enums
+  #[napi(factory)]
+  pub fn from_json(env: Env, json: String) -> napi::Result<Self> {
+    let inner = guard(
+      env,
+      "ReplSession.fromJson",
+      || "the session JSON".into(),
+      || ReplSession::from_json(json),
+    )?;
+    Ok(JsReplSession { inner })
+  }
+
+  /// Writes the session to `path`, creating its directory if needed
+  #[napi]
+  pub fn save(&self, env: Env, path: String) -> napi::Result<()> {
+    guard(
+      env,
+      "ReplSession.save",
+      || path.clone(),
+      || self.inner.save(path.clone()),
+    )
+  }
+
+  /// Reads a session written by `save`, like `fromJson`
+  #[napi(factory)]
+  pub fn load(env: Env, path: String) -> napi::Result<Self> {
+    let inner = guard(
+      env,
+      "ReplSession.load",
+      || path.clone(),
+      || ReplSession::load(path.clone()),
+    )?;
+    Ok(JsReplSession { inner })
+  }
+}
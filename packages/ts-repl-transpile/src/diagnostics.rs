@@ -0,0 +1,153 @@
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use swc_core::common::errors::{DiagnosticBuilder, Emitter, Handler, Level, SubDiagnostic};
+use swc_core::common::{SourceMap, Span};
+
+/// A location in the evaluated source. Offsets are 0-based bytes, lines are
+/// 1-based and columns are 0-based characters.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+pub struct DiagnosticSpan {
+  pub start: u32,
+  pub end: u32,
+  pub start_line: u32,
+  pub start_column: u32,
+  pub end_line: u32,
+  pub end_column: u32,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub label: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+pub struct Diagnostic {
+  pub message: String,
+  /// "error", "warning", "note" or "help"
+  pub severity: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub code: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub span: Option<DiagnosticSpan>,
+  /// Secondary labels and notes attached to the diagnostic
+  pub related: Vec<DiagnosticSpan>,
+}
+
+impl Diagnostic {
+  pub fn is_error(&self) -> bool {
+    self.severity == "error"
+  }
+}
+
+/// Diagnostics collected by a [Handler] made with [handler]. Cloning shares
+/// the underlying list.
+#[derive(Clone, Default)]
+pub struct Diagnostics(Arc<Mutex<Vec<Diagnostic>>>);
+
+impl Diagnostics {
+  pub fn push(&self, diagnostic: Diagnostic) {
+    self.0.lock().unwrap().push(diagnostic);
+  }
+
+  pub fn has_errors(&self) -> bool {
+    self.0.lock().unwrap().iter().any(Diagnostic::is_error)
+  }
+
+  pub fn take(&self) -> Vec<Diagnostic> {
+    std::mem::take(&mut *self.0.lock().unwrap())
+  }
+
+  /// Drains the list for an output struct, where nothing to report is
+  /// represented by leaving the field out
+  pub fn take_option(&self) -> Option<Vec<Diagnostic>> {
+    let diagnostics = self.take();
+    if diagnostics.is_empty() {
+      None
+    } else {
+      Some(diagnostics)
+    }
+  }
+}
+
+fn severity(level: Level) -> &'static str {
+  match level {
+    Level::Warning => "warning",
+    Level::Note => "note",
+    Level::Help => "help",
+    _ => "error",
+  }
+}
+
+fn to_diagnostic_span(cm: &SourceMap, span: Span, label: Option<String>) -> Option<DiagnosticSpan> {
+  if span.is_dummy() {
+    return None;
+  }
+  let lo = cm.lookup_char_pos(span.lo);
+  let hi = cm.lookup_char_pos(span.hi);
+  Some(DiagnosticSpan {
+    start: span.lo.0 - lo.file.start_pos.0,
+    end: span.hi.0 - hi.file.start_pos.0,
+    start_line: lo.line as u32,
+    start_column: lo.col.0 as u32,
+    end_line: hi.line as u32,
+    end_column: hi.col.0 as u32,
+    label,
+  })
+}
+
+fn child_spans(cm: &SourceMap, child: &SubDiagnostic) -> Vec<DiagnosticSpan> {
+  child
+    .span
+    .primary_spans()
+    .iter()
+    .filter_map(|span| to_diagnostic_span(cm, *span, Some(child.message())))
+    .collect()
+}
+
+struct CollectingEmitter {
+  cm: Arc<SourceMap>,
+  diagnostics: Diagnostics,
+}
+
+impl Emitter for CollectingEmitter {
+  fn emit(&mut self, db: &DiagnosticBuilder<'_>) {
+    let mut span = None;
+    let mut related = vec![];
+    for label in db.span.span_labels() {
+      if label.is_primary && span.is_none() {
+        span = to_diagnostic_span(&self.cm, label.span, label.label);
+      } else if let Some(s) = to_diagnostic_span(&self.cm, label.span, label.label) {
+        related.push(s);
+      }
+    }
+    for child in db.children.iter() {
+      related.extend(child_spans(&self.cm, child));
+    }
+
+    self.diagnostics.push(Diagnostic {
+      message: db.message(),
+      severity: severity(db.level).into(),
+      code: db.code.as_ref().map(|code| match code {
+        swc_core::common::errors::DiagnosticId::Error(s) => s.clone(),
+        swc_core::common::errors::DiagnosticId::Lint(s) => s.clone(),
+      }),
+      span,
+      related,
+    });
+  }
+}
+
+/// Creates a handler that records everything emitted through it instead of
+/// printing to the extension host's stderr
+pub fn handler(cm: Arc<SourceMap>) -> (Handler, Diagnostics) {
+  let diagnostics = Diagnostics::default();
+  let emitter = CollectingEmitter {
+    cm,
+    diagnostics: diagnostics.clone(),
+  };
+  (
+    Handler::with_emitter(true, false, Box::new(emitter)),
+    diagnostics,
+  )
+}
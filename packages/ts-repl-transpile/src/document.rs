@@ -0,0 +1,340 @@
+//! A document parsed to find the evaluable forms in it, for `evaluable_spans`
+//! and `select_forms`. Targets come in and spans go out in the document's
+//! `offsetEncoding`; in between everything is a swc position.
+
+use crate::diagnostics::{self, Diagnostic};
+use crate::error::{Error, Result};
+use crate::evaluable_spans::{FindNeighbors, Neighbor, Target};
+use crate::line_index::LineIndex;
+use crate::options::{self, OffsetEncoding, TransformOptions};
+use std::borrow::Borrow;
+use std::sync::Arc;
+use swc_core::common::errors::Handler;
+use swc_core::common::input::{SourceFileInput, StringInput};
+use swc_core::common::{BytePos, SourceFile, SourceMap, Span, DUMMY_SP, GLOBALS};
+use swc_core::ecma::ast::Module;
+use swc_core::ecma::parser::lexer::Lexer;
+use swc_core::ecma::parser::token::{BinOpToken, Keyword, Token, TokenAndSpan, Word};
+use swc_core::ecma::parser::{Parser, Syntax};
+use swc_core::ecma::visit::{Visit, VisitMutWith, VisitWith};
+
+pub(crate) struct Document {
+  source_file: Arc<SourceFile>,
+  /// The parts of the source that could be parsed
+  module: Module,
+  diagnostics: Vec<Diagnostic>,
+  encoding: Option<OffsetEncoding>,
+  /// Where each node of `module` starts, in order, once `next_node` needs it
+  node_starts: Option<Vec<u32>>,
+}
+
+impl Document {
+  pub fn parse(source: String, options: Option<TransformOptions>) -> Result<Self> {
+    let opts = options::resolve(options)?;
+    let cm: Arc<SourceMap> = Default::default();
+
+    Ok(GLOBALS.set(&Default::default(), || {
+      let (handler, diagnostics) = diagnostics::handler(cm.clone());
+      let source_file = cm.new_source_file(opts.file_name.clone(), source);
+      let input = SourceFileInput::from(source_file.borrow());
+
+      let (module, diagnostics) = match parse_module(opts.syntax, input, &handler) {
+        Some(module) => (module, diagnostics.take()),
+        None => parse_chunks(&cm, &source_file, opts.syntax, diagnostics.take()),
+      };
+
+      Document {
+        source_file,
+        module,
+        diagnostics,
+        encoding: opts.offset_encoding,
+        node_starts: None,
+      }
+    }))
+  }
+
+  fn index(&self) -> LineIndex<'_> {
+    LineIndex::new(&self.source_file.src)
+  }
+
+  /// The swc position of the character before the cursor at `target`
+  pub fn cursor(&self, target: Target) -> Result<u32> {
+    let index = self.index();
+    Ok(match self.locate(target, &index)? {
+      Located::Offset(offset) => {
+        self.source_file.start_pos.0 + index.with_bom(index.before(offset))
+      }
+      Located::Pos(pos) => pos,
+    })
+  }
+
+  /// The swc position of `target` as one end of a selection, which is the
+  /// position of the character after it
+  pub fn boundary(&self, target: Target) -> Result<u32> {
+    let index = self.index();
+    Ok(match self.locate(target, &index)? {
+      Located::Offset(offset) => self.source_file.start_pos.0 + index.with_bom(offset),
+      Located::Pos(pos) => pos,
+    })
+  }
+
+  fn locate(&self, target: Target, index: &LineIndex) -> Result<Located> {
+    Ok(Located::Offset(match (target, self.encoding) {
+      (Target::Offset(pos), None) => return Ok(Located::Pos(pos)),
+      (Target::Offset(offset), Some(OffsetEncoding::Bytes)) => offset,
+      (Target::Offset(offset), Some(OffsetEncoding::Utf16)) => index.offset_of_utf16(offset),
+      (Target::Offset(_), Some(OffsetEncoding::LineColumn)) => {
+        return Err(Error::invalid_arg(
+          "With offsetEncoding \"lineColumn\" the target must be a {line, character} position",
+        ))
+      }
+      (Target::Position(position), _) => index.offset_of_position(position),
+    }))
+  }
+
+  /// The source between swc positions `start` and `end`, or nothing when
+  /// they aren't in it
+  fn text(&self, start: u32, end: u32) -> &str {
+    let base = self.source_file.start_pos.0;
+    let range = start.saturating_sub(base) as usize..end.saturating_sub(base) as usize;
+    self.source_file.src.get(range).unwrap_or_default()
+  }
+
+  /// `start` to `end` without the whitespace at either end
+  pub fn trim(&self, start: u32, end: u32) -> (u32, u32) {
+    let text = self.text(start, end);
+    let start = start + (text.len() - text.trim_start().len()) as u32;
+    (start, start + text.trim().len() as u32)
+  }
+
+  /// The position of the first character at or after `pos` that isn't
+  /// whitespace
+  pub fn skip_whitespace(&self, pos: u32) -> u32 {
+    let rest = self.text(pos, self.source_file.end_pos.0);
+    pos + (rest.len() - rest.trim_start().len()) as u32
+  }
+
+  /// The swc position of the first node that starts after `pos`, or `end`
+  /// when none does before it
+  pub fn next_node(&mut self, pos: u32, end: u32) -> u32 {
+    let module = &self.module;
+    let starts = self.node_starts.get_or_insert_with(|| {
+      let mut starts = NodeStarts(vec![]);
+      module.visit_with(&mut starts);
+      starts.0.sort_unstable();
+      starts.0.dedup();
+      starts.0
+    });
+    let next = starts.partition_point(|&start| start <= pos);
+    starts
+      .get(next)
+      .copied()
+      .filter(|&start| start < end)
+      .unwrap_or(end)
+  }
+
+  /// The forms around the character at swc position `pos`, outermost first.
+  /// None when it is in a part of the document that could not be parsed.
+  pub fn neighbors(&mut self, pos: u32) -> Vec<Neighbor> {
+    let mut find = FindNeighbors {
+      neighbors: vec![],
+      target_loc: pos,
+    };
+    self.module.visit_mut_with(&mut find);
+    find.into_spans(&self.source_file.src, self.source_file.start_pos)
+  }
+
+  /// Rewrites the positions in `spans` from swc's into the encoding's
+  pub fn encode(&self, spans: &mut [Neighbor]) {
+    let encoding = match self.encoding {
+      Some(encoding) => encoding,
+      None => return,
+    };
+    let index = self.index();
+    let start_pos = self.source_file.start_pos.0;
+    for span in spans {
+      let start = index.without_bom(span.start - start_pos);
+      let end = index.without_bom(span.end - start_pos);
+      if encoding == OffsetEncoding::LineColumn {
+        span.range = Some(index.range(start, end));
+      }
+      span.start = encode(&index, encoding, start);
+      span.end = encode(&index, encoding, end);
+    }
+  }
+
+  /// What parsing reported, in the encoding, or `None` when that is nothing
+  pub fn take_diagnostics(&mut self) -> Option<Vec<Diagnostic>> {
+    let mut diagnostics = std::mem::take(&mut self.diagnostics);
+    if diagnostics.is_empty() {
+      return None;
+    }
+    let encoding = match self.encoding {
+      Some(encoding) => encoding,
+      None => return Some(diagnostics),
+    };
+    let index = self.index();
+    // these are already bytes from the start of the file, with 1-based lines
+    let spans = diagnostics
+      .iter_mut()
+      .flat_map(|diagnostic| diagnostic.span.iter_mut().chain(&mut diagnostic.related));
+    for span in spans {
+      let start = index.without_bom(span.start);
+      let end = index.without_bom(span.end);
+      if encoding != OffsetEncoding::Bytes {
+        let (start_position, end_position) = (index.position(start), index.position(end));
+        span.start_line = start_position.line + 1;
+        span.start_column = start_position.character;
+        span.end_line = end_position.line + 1;
+        span.end_column = end_position.character;
+      }
+      span.start = encode(&index, encoding, start);
+      span.end = encode(&index, encoding, end);
+    }
+    Some(diagnostics)
+  }
+}
+
+/// Collects where every span of a module starts
+struct NodeStarts(Vec<u32>);
+
+impl Visit for NodeStarts {
+  fn visit_span(&mut self, span: &Span) {
+    if !span.is_dummy() {
+      self.0.push(span.lo.0);
+    }
+  }
+}
+
+/// Parses `input` as a module, emitting what is wrong with it to `handler`.
+/// `None` when it can't be parsed.
+fn parse_module(syntax: Syntax, input: StringInput, handler: &Handler) -> Option<Module> {
+  let lexer = Lexer::new(syntax, Default::default(), input, None);
+  let mut parser = Parser::new_from(lexer);
+  let module = parser
+    .parse_module()
+    .map_err(|err| err.into_diagnostic(handler).emit())
+    .ok();
+  for err in parser.take_errors() {
+    err.into_diagnostic(handler).emit();
+  }
+  module
+}
+
+/// Parses each of the top-level chunks of a document that doesn't parse as a
+/// whole, since while typing most of it usually does. The module has what
+/// could be parsed, and the errors are the chunks', which say where the
+/// problems are better, unless splitting hid them.
+fn parse_chunks(
+  cm: &Arc<SourceMap>,
+  source_file: &SourceFile,
+  syntax: Syntax,
+  errors: Vec<Diagnostic>,
+) -> (Module, Vec<Diagnostic>) {
+  let (handler, diagnostics) = diagnostics::handler(cm.clone());
+  let body = top_level_chunks(source_file, syntax)
+    .into_iter()
+    .filter_map(|(lo, hi)| {
+      let code = &source_file.src[offset(source_file, lo)..offset(source_file, hi)];
+      parse_module(syntax, StringInput::new(code, lo, hi), &handler)
+    })
+    .flat_map(|module| module.body)
+    .collect();
+  let module = Module {
+    span: DUMMY_SP,
+    body,
+    shebang: None,
+  };
+  let diagnostics = diagnostics.take();
+  if diagnostics.iter().any(Diagnostic::is_error) {
+    (module, diagnostics)
+  } else {
+    (module, errors)
+  }
+}
+
+fn offset(source_file: &SourceFile, pos: BytePos) -> usize {
+  (pos.0 - source_file.start_pos.0) as usize
+}
+
+/// Splits `source_file` into the chunks that are likely top-level statements.
+/// A chunk starts on each line that starts with a token that can start a
+/// statement, when that is outside any brackets, or with a keyword that
+/// starts a declaration, which is how a bracket left open is recovered from.
+fn top_level_chunks(source_file: &SourceFile, syntax: Syntax) -> Vec<(BytePos, BytePos)> {
+  let lexer = Lexer::new(
+    syntax,
+    Default::default(),
+    SourceFileInput::from(source_file),
+    None,
+  );
+  let text = source_file.src.as_bytes();
+  let mut starts = vec![source_file.start_pos];
+  let mut depth = 0u32;
+  for TokenAndSpan { token, span, .. } in lexer {
+    let at = offset(source_file, span.lo);
+    let at_line_start = at > 0 && matches!(text[at - 1], b'\n' | b'\r');
+    if at_line_start && (starts_declaration(&token) || (depth == 0 && starts_statement(&token))) {
+      starts.push(span.lo);
+      depth = 0;
+    }
+    match token {
+      Token::LParen | Token::LBracket | Token::LBrace | Token::DollarLBrace => depth += 1,
+      Token::RParen | Token::RBracket | Token::RBrace => depth = depth.saturating_sub(1),
+      _ => {}
+    }
+  }
+  let ends = starts[1..].iter().copied().chain([source_file.end_pos]);
+  starts.iter().copied().zip(ends).collect()
+}
+
+/// Whether a line starting with `token` can be a new statement rather than
+/// the rest of the one before
+fn starts_statement(token: &Token) -> bool {
+  match token {
+    Token::RParen
+    | Token::RBracket
+    | Token::RBrace
+    | Token::Dot
+    | Token::Comma
+    | Token::Colon
+    | Token::QuestionMark
+    | Token::Arrow
+    | Token::AssignOp(_)
+    | Token::Error(_) => false,
+    Token::Word(Word::Keyword(Keyword::Else | Keyword::Catch | Keyword::Finally)) => false,
+    // these also start unary expressions and JSX
+    Token::BinOp(op) => matches!(op, BinOpToken::Add | BinOpToken::Sub | BinOpToken::Lt),
+    _ => true,
+  }
+}
+
+fn starts_declaration(token: &Token) -> bool {
+  matches!(
+    token,
+    Token::Word(Word::Keyword(
+      Keyword::Const
+        | Keyword::Let
+        | Keyword::Var
+        | Keyword::Function
+        | Keyword::Class
+        | Keyword::Import
+        | Keyword::Export
+    ))
+  )
+}
+
+/// Where a target is
+enum Located {
+  /// A byte offset in the document
+  Offset(u32),
+  /// A swc position, which is what offsets are without an encoding
+  Pos(u32),
+}
+
+fn encode(index: &LineIndex, encoding: OffsetEncoding, offset: u32) -> u32 {
+  match encoding {
+    OffsetEncoding::Bytes => offset,
+    OffsetEncoding::Utf16 | OffsetEncoding::LineColumn => index.utf16(offset),
+  }
+}
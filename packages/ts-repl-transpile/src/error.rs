@@ -0,0 +1,71 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+  /// The caller passed something unusable: bad options, a bad source map,
+  /// an unreadable session
+  InvalidArg,
+  /// Something went wrong that the input can't be blamed for
+  Failure,
+  /// The transpiler panicked, which is a bug in it
+  Internal,
+}
+
+/// Why a call failed outright. Problems with the source itself are reported
+/// as diagnostics on the output instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+  pub kind: ErrorKind,
+  pub message: String,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+  pub fn invalid_arg(message: impl Into<String>) -> Self {
+    Error {
+      kind: ErrorKind::InvalidArg,
+      message: message.into(),
+    }
+  }
+
+  pub fn failure(message: impl Into<String>) -> Self {
+    Error {
+      kind: ErrorKind::Failure,
+      message: message.into(),
+    }
+  }
+
+  pub fn internal(message: impl Into<String>) -> Self {
+    Error {
+      kind: ErrorKind::Internal,
+      message: message.into(),
+    }
+  }
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(&self.message)
+  }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(feature = "napi")]
+impl From<Error> for napi::Error {
+  fn from(err: Error) -> Self {
+    let status = match err.kind {
+      ErrorKind::InvalidArg => napi::Status::InvalidArg,
+      ErrorKind::Failure | ErrorKind::Internal => napi::Status::GenericFailure,
+    };
+    napi::Error::new(status, err.message)
+  }
+}
+
+#[cfg(feature = "wasm")]
+impl From<Error> for wasm_bindgen::JsValue {
+  fn from(err: Error) -> Self {
+    wasm_bindgen::JsError::new(&err.message).into()
+  }
+}
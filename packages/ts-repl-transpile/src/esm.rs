@@ -0,0 +1,125 @@
+use crate::session::{decl_names, top_level_bindings};
+use std::collections::HashSet;
+use swc_core::common::DUMMY_SP;
+use swc_core::ecma::ast::*;
+use swc_core::ecma::visit::{Visit, VisitWith};
+
+// Rewrites for `module.type: "es"`. Every snippet becomes its own module, so
+// nothing it declares is visible to the next one unless it is exported and
+// imported again. The extension links `import ... from "<namespace>"` to what
+// earlier snippets of the namespace exported.
+
+fn export_name(name: &ModuleExportName) -> String {
+  match name {
+    ModuleExportName::Ident(i) => i.sym.to_string(),
+    ModuleExportName::Str(s) => s.value.to_string(),
+  }
+}
+
+fn exported_names(module: &Module) -> HashSet<String> {
+  let mut names = HashSet::new();
+  for item in &module.body {
+    if let ModuleItem::ModuleDecl(decl) = item {
+      match decl {
+        ModuleDecl::ExportDecl(export) => {
+          let mut declared = vec![];
+          decl_names(&export.decl, &mut declared);
+          names.extend(declared);
+        }
+        ModuleDecl::ExportNamed(named) => {
+          for specifier in &named.specifiers {
+            names.insert(match specifier {
+              ExportSpecifier::Named(s) => export_name(s.exported.as_ref().unwrap_or(&s.orig)),
+              ExportSpecifier::Namespace(s) => export_name(&s.name),
+              ExportSpecifier::Default(s) => s.exported.sym.to_string(),
+            });
+          }
+        }
+        ModuleDecl::ExportDefaultDecl(_) | ModuleDecl::ExportDefaultExpr(_) => {
+          names.insert("default".into());
+        }
+        _ => {}
+      }
+    }
+  }
+  names
+}
+
+/// Exports every top level binding that isn't exported already, so later
+/// snippets can import it
+pub fn export_top_level_bindings(module: &mut Module) {
+  let exported = exported_names(module);
+  let specifiers: Vec<ExportSpecifier> = top_level_bindings(module)
+    .into_iter()
+    .filter(|name| !exported.contains(name))
+    .map(|name| {
+      ExportSpecifier::Named(ExportNamedSpecifier {
+        span: DUMMY_SP,
+        orig: ModuleExportName::Ident(Ident::new(name.into(), DUMMY_SP)),
+        exported: None,
+        is_type_only: false,
+      })
+    })
+    .collect();
+  if specifiers.is_empty() {
+    return;
+  }
+  module
+    .body
+    .push(ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(
+      NamedExport {
+        span: DUMMY_SP,
+        specifiers,
+        src: None,
+        type_only: false,
+        asserts: None,
+      },
+    )));
+}
+
+#[derive(Default)]
+struct UsedNames(HashSet<String>);
+
+impl Visit for UsedNames {
+  fn visit_ident(&mut self, n: &Ident) {
+    self.0.insert(n.sym.to_string());
+  }
+}
+
+/// Imports the bindings `module` uses from the ones earlier snippets of the
+/// namespace declared. Names the snippet declares itself are left alone.
+pub fn import_namespace_bindings(module: &mut Module, namespace: &str, bindings: &[String]) {
+  let mut used = UsedNames::default();
+  module.visit_with(&mut used);
+  let declared = top_level_bindings(module);
+
+  let specifiers: Vec<ImportSpecifier> = bindings
+    .iter()
+    .filter(|name| used.0.contains(*name) && !declared.contains(name))
+    .map(|name| {
+      ImportSpecifier::Named(ImportNamedSpecifier {
+        span: DUMMY_SP,
+        local: Ident::new(name.as_str().into(), DUMMY_SP),
+        imported: None,
+        is_type_only: false,
+      })
+    })
+    .collect();
+  if specifiers.is_empty() {
+    return;
+  }
+  module.body.insert(
+    0,
+    ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl {
+      span: DUMMY_SP,
+      specifiers,
+      src: Box::new(Str {
+        span: DUMMY_SP,
+        value: namespace.into(),
+        raw: None,
+      }),
+      type_only: false,
+      asserts: None,
+    })),
+  );
+}
@@ -3,14 +3,236 @@ use swc_core::common::{BytePos, Span, Spanned};
 use swc_core::ecma::visit::{VisitMut, VisitMutWith};
 use swc_core::ecma::ast::*;
 use serde::Serialize;
-use log::{debug};
+use crate::line_index::{Position, Range};
+
+/// What kind of node a `Neighbor` is. The names follow the ESTree ones where
+/// there is one.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+// napi derives these itself
+#[cfg_attr(not(feature = "napi"), derive(Clone, Copy))]
+#[cfg_attr(feature = "napi", napi_derive::napi(string_enum))]
+pub enum NodeKind {
+    // statements
+    Statement,
+    ExpressionStatement,
+    ReturnStatement,
+    BlockStatement,
+    WithStatement,
+    LabeledStatement,
+    IfStatement,
+    SwitchStatement,
+    SwitchCase,
+    ThrowStatement,
+    TryStatement,
+    WhileStatement,
+    DoWhileStatement,
+    ForStatement,
+    ForInStatement,
+    ForOfStatement,
+
+    // expressions
+    AwaitExpression,
+    ArrayLiteral,
+    ArrowFunctionExpression,
+    AssignmentExpression,
+    BinaryExpression,
+    MemberExpression,
+    SuperPropExpression,
+    ConditionalExpression,
+    CallExpression,
+    OptionalCallExpression,
+    NewExpression,
+    SequenceExpression,
+    Identifier,
+    ClassExpression,
+    YieldExpression,
+    FunctionExpression,
+    Function,
+    UpdateExpression,
+    MetaProperty,
+    ParenthesisExpression,
+    OptionalChainingExpression,
+    TaggedTemplateExpression,
+    ThisExpression,
+    UnaryExpression,
+
+    // literals
+    ObjectExpression,
+    StringLiteral,
+    BooleanLiteral,
+    NullLiteral,
+    NumericLiteral,
+    BigIntLiteral,
+    RegExpLiteral,
+    TemplateLiteral,
+
+    // classes
+    ClassMethod,
+    ClassMember,
+    ClassProp,
+    ComputedPropertyName,
+    Constructor,
+    Decorator,
+
+    // declarations
+    VariableDeclaration,
+    VariableDeclarator,
+    ClassDeclaration,
+    FunctionDeclaration,
+    Parameter,
+    TsEnumDeclaration,
+    TsEnumMember,
+
+    // patterns and properties
+    ArrayPattern,
+    RestElement,
+    ObjectPattern,
+    AssignPattern,
+    KeyValueProperty,
+    KeyValuePatternProperty,
+
+    // modules
+    ModuleDeclaration,
+    Import,
+    ImportDeclaration,
+    ImportNamespaceSpecifier,
+    ImportSpecifier,
+    ImportDefaultSpecifier,
+    ExportDeclaration,
+    ExportAllDeclaration,
+    ExportDefaultDeclaration,
+    ExportSpecifier,
+    ExportDefaultSpecifier,
+    ExportNamespaceSpecifier,
+    ExportNamedDeclaration,
 
+    // jsx
+    JSXElement,
+    JSXFragment,
+    JSXAttribute,
+    JSXExpressionContainer,
+    JSXMemberExpression,
+}
+
+impl NodeKind {
+    /// Whether nodes of this kind are expressions, which evaluate to a value
+    pub fn is_expression(self) -> bool {
+        use NodeKind::*;
+        matches!(
+            self,
+            AwaitExpression
+                | ArrayLiteral
+                | ArrowFunctionExpression
+                | AssignmentExpression
+                | BinaryExpression
+                | MemberExpression
+                | SuperPropExpression
+                | ConditionalExpression
+                | CallExpression
+                | OptionalCallExpression
+                | NewExpression
+                | SequenceExpression
+                | Identifier
+                | ClassExpression
+                | YieldExpression
+                | FunctionExpression
+                | UpdateExpression
+                | MetaProperty
+                | ParenthesisExpression
+                | OptionalChainingExpression
+                | TaggedTemplateExpression
+                | ThisExpression
+                | UnaryExpression
+                | ObjectExpression
+                | StringLiteral
+                | BooleanLiteral
+                | NullLiteral
+                | NumericLiteral
+                | BigIntLiteral
+                | RegExpLiteral
+                | TemplateLiteral
+                | JSXElement
+                | JSXFragment
+                | JSXMemberExpression
+        )
+    }
+}
+
+/// A node around the target. `parent` is the index of the innermost other
+/// span that contains this one, and `depth` how many spans do.
 #[derive(Debug, Serialize)]
-#[napi_derive::napi(object)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
 pub struct Neighbor {
     pub start: u32,
     pub end: u32,
-    pub r#type: &'static str,
+    pub r#type: NodeKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<u32>,
+    pub depth: u32,
+    /// The binding this declares, for declarations of a single one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The node's source on one line, cut to 40 chars
+    pub preview: String,
+    /// Where the node is as lines and characters, when the `offsetEncoding`
+    /// is "lineColumn"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub range: Option<Range>,
+}
+
+/// Where `evaluable_spans` looks: an offset in the `offsetEncoding`, or a
+/// line and character whatever the encoding. Either way it is a cursor, and
+/// what is around the character before it is found, so a cursor just after
+/// a form finds that form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Offset(u32),
+    Position(Position),
+}
+
+impl From<u32> for Target {
+    fn from(offset: u32) -> Self {
+        Target::Offset(offset)
+    }
+}
+
+impl From<Position> for Target {
+    fn from(position: Position) -> Self {
+        Target::Position(position)
+    }
+}
+
+impl Neighbor {
+    fn new(span: Span, kind: NodeKind, name: Option<String>) -> Self {
+        Neighbor {
+            start: span.lo.0,
+            end: span.hi.0,
+            r#type: kind,
+            parent: None,
+            depth: 0,
+            name,
+            preview: String::new(),
+            range: None,
+        }
+    }
+}
+
+pub const PREVIEW_LENGTH: usize = 40;
+
+fn preview(code: &str) -> String {
+    let mut preview = String::new();
+    for (i, word) in code.split_whitespace().enumerate() {
+        if i > 0 {
+            preview.push(' ');
+        }
+        preview.push_str(word);
+    }
+    if preview.chars().count() > PREVIEW_LENGTH {
+        preview = preview.chars().take(PREVIEW_LENGTH - 1).collect();
+        preview.push('…');
+    }
+    preview
 }
 
 pub struct FindNeighbors {
@@ -18,172 +240,221 @@ pub struct FindNeighbors {
     pub neighbors: Vec<Neighbor>,
 }
 
+impl FindNeighbors {
+    /// The neighbors found, with parents, depths and previews filled in from
+    /// `source`, the code of the file starting at `start_pos`
+    pub fn into_spans(self, source: &str, start_pos: BytePos) -> Vec<Neighbor> {
+        let mut spans = self.neighbors;
+        for i in 0..spans.len() {
+            // found outermost first, so whatever contains a span is before it
+            let parent = (0..i).rev().find(|&j| {
+                spans[j].start <= spans[i].start && spans[i].end <= spans[j].end
+            });
+            spans[i].parent = parent.map(|j| j as u32);
+            spans[i].depth = parent.map_or(0, |j| spans[j].depth + 1);
+            let code = source.get(
+                (spans[i].start - start_pos.0) as usize..(spans[i].end - start_pos.0) as usize,
+            );
+            spans[i].preview = preview(code.unwrap_or_default());
+        }
+        spans
+    }
+}
+
+fn ident_name(ident: &Ident) -> Option<String> {
+    Some(ident.sym.to_string())
+}
+
+fn pat_name(pat: &Pat) -> Option<String> {
+    match pat {
+        Pat::Ident(i) => ident_name(&i.id),
+        _ => None,
+    }
+}
+
+fn var_decl_name(n: &VarDecl) -> Option<String> {
+    match n.decls.as_slice() {
+        [declarator] => pat_name(&declarator.name),
+        _ => None,
+    }
+}
+
+fn decl_name(n: &Decl) -> Option<String> {
+    match n {
+        Decl::Class(x) => ident_name(&x.ident),
+        Decl::Fn(x) => ident_name(&x.ident),
+        Decl::Var(x) => var_decl_name(x),
+        Decl::TsEnum(x) => ident_name(&x.id),
+        Decl::TsInterface(x) => ident_name(&x.id),
+        Decl::TsTypeAlias(x) => ident_name(&x.id),
+        _ => None,
+    }
+}
+
+fn export_default_decl_name(n: &ExportDefaultDecl) -> Option<String> {
+    match &n.decl {
+        DefaultDecl::Class(x) => x.ident.as_ref().and_then(ident_name),
+        DefaultDecl::Fn(x) => x.ident.as_ref().and_then(ident_name),
+        DefaultDecl::TsInterfaceDecl(x) => ident_name(&x.id),
+    }
+}
+
+fn module_decl_name(n: &ModuleDecl) -> Option<String> {
+    match n {
+        ModuleDecl::ExportDecl(x) => decl_name(&x.decl),
+        ModuleDecl::ExportDefaultDecl(x) => export_default_decl_name(x),
+        _ => None,
+    }
+}
+
+fn stmt_name(n: &Stmt) -> Option<String> {
+    match n {
+        Stmt::Decl(x) => decl_name(x),
+        _ => None,
+    }
+}
 
 impl From<&mut ModuleDecl> for Neighbor {
     fn from(value: &mut ModuleDecl) -> Self {
-        Neighbor {
-            start: value.span_lo().0,
-            end: value.span_hi().0,
-            r#type: "ModuleDecl",
-        }
+        Neighbor::new(value.span(), NodeKind::ModuleDeclaration, module_decl_name(value))
     }
 }
 
+// `$name` is how to get the binding a node declares, for declarations
 macro_rules! impl_from {
-    ($typ:ty, $name:expr) => {
+    ($typ:ty, $kind:ident) => {
+        impl_from!($typ, $kind, |_| None);
+    };
+    ($typ:ty, $kind:ident, $name:expr) => {
         impl From<&mut $typ> for Neighbor {
             fn from(value: &mut $typ) -> Self {
-                let n = Neighbor {
-                    start: value.span_lo().0,
-                    end: value.span_hi().0,
-                    r#type: $name,
-                };
-                debug!("heyo {:?}", &n);
-                n
+                let name: fn(&$typ) -> Option<String> = $name;
+                Neighbor::new(value.span(), NodeKind::$kind, name(value))
             }
         }
-
     };
 }
 macro_rules! impl_from_boxed {
-    ($typ:ty, $name:expr) => {
+    ($typ:ty, $kind:ident) => {
+        impl_from_boxed!($typ, $kind, |_| None);
+    };
+    ($typ:ty, $kind:ident, $name:expr) => {
         impl From<&mut Box<$typ>> for Neighbor {
             fn from(value: &mut Box<$typ>) -> Self {
-                let n = Neighbor {
-                    start: value.span_lo().0,
-                    end: value.span_hi().0,
-                    r#type: $name,
-                };
-                debug!("heyo {:?}", &n);
-                n
-            }
-        }
-
-    };
-}
-macro_rules! impl_from_option_boxed {
-    ($typ:ty, $name:expr) => {
-        impl From<&mut Option<Box<$typ>>> for Neighbor {
-            fn from(opt: &mut Option<Box<$typ>>) -> Self {
-                match opt {
-                    None => {},
-                    Some(value) => {
-                        let n = Neighbor {
-                            start: value.span_lo().0,
-                            end: value.span_hi().0,
-                            r#type: $name,
-                          };
-                        // println!("heyo {:?}", &n);
-                        n
-                    }
-                }
+                let name: fn(&$typ) -> Option<String> = $name;
+                Neighbor::new(value.span(), NodeKind::$kind, name(value))
             }
         }
     };
 }
 
 // structs with spans that are evaluable
-impl_from!(Stmt, "Statement");
-impl_from!(ReturnStmt, "ReturnStatement");
-impl_from!(BlockStmt, "BlockStatement");
-impl_from!(WithStmt, "WithStatement");
-impl_from!(LabeledStmt, "LabeledStatement");
-impl_from!(IfStmt, "IfStatement");
-impl_from!(SwitchStmt, "IfStatement");
-impl_from!(ThrowStmt, "ThrowStatement");
-impl_from!(TryStmt, "TryStatement");
-impl_from!(WhileStmt, "WhileStatement");
-impl_from!(DoWhileStmt, "DoWhileStatement");
-impl_from!(ForStmt, "ForStatement");
-impl_from!(ForInStmt, "ForInStatement");
-impl_from!(ForOfStmt, "ForOfStatement");
-impl_from!(AwaitExpr, "AwaitExpression");
-impl_from!(ArrayLit, "ArrayLiteral");
-impl_from!(ArrowExpr, "ArrowFunctionExpression");
-impl_from!(AssignExpr, "AssignmentExpression");
-impl_from!(BinExpr, "BinaryExpression");
-impl_from!(MemberExpr, "MemberExpression");
-
-impl_from!(SuperPropExpr, "SuperPropExpression");
-impl_from!(CondExpr, "ConditionalExpression");
-impl_from!(CallExpr, "CallExpression");
-impl_from!(NewExpr, "NewExpression");
-impl_from!(SeqExpr, "SequenceExpression");
-impl_from!(Ident, "Identifier");
-impl_from!(ClassExpr, "ClassExpression");
-impl_from!(YieldExpr, "YieldExpression");
-impl_from!(FnExpr, "FunctionExpression");
-impl_from!(Function, "Function");
-impl_from!(UpdateExpr, "UpdateExpression");
-
-
-impl_from!(MetaPropExpr, "MetaProperty");
-impl_from!(ParenExpr, "ParenthesisExpression");
-impl_from!(OptChainExpr, "OptionalChainingExpression");
-impl_from!(SwitchCase, "SwitchCase");
-
-impl_from!(OptCall, "CallExpression");
+impl_from!(Stmt, Statement, stmt_name);
+impl_from!(ReturnStmt, ReturnStatement);
+impl_from!(BlockStmt, BlockStatement);
+impl_from!(WithStmt, WithStatement);
+impl_from!(LabeledStmt, LabeledStatement);
+impl_from!(IfStmt, IfStatement);
+impl_from!(SwitchStmt, SwitchStatement);
+impl_from!(ThrowStmt, ThrowStatement);
+impl_from!(TryStmt, TryStatement);
+impl_from!(WhileStmt, WhileStatement);
+impl_from!(DoWhileStmt, DoWhileStatement);
+impl_from!(ForStmt, ForStatement);
+impl_from!(ForInStmt, ForInStatement);
+impl_from!(ForOfStmt, ForOfStatement);
+impl_from!(AwaitExpr, AwaitExpression);
+impl_from!(ArrayLit, ArrayLiteral);
+impl_from!(ArrowExpr, ArrowFunctionExpression);
+impl_from!(AssignExpr, AssignmentExpression);
+impl_from!(BinExpr, BinaryExpression);
+impl_from!(MemberExpr, MemberExpression);
+
+impl_from!(SuperPropExpr, SuperPropExpression);
+impl_from!(CondExpr, ConditionalExpression);
+impl_from!(CallExpr, CallExpression);
+impl_from!(NewExpr, NewExpression);
+impl_from!(SeqExpr, SequenceExpression);
+impl_from!(Ident, Identifier);
+impl_from!(ClassExpr, ClassExpression);
+impl_from!(YieldExpr, YieldExpression);
+impl_from!(FnExpr, FunctionExpression);
+impl_from!(Function, Function);
+impl_from!(UpdateExpr, UpdateExpression);
+
+
+impl_from!(MetaPropExpr, MetaProperty);
+impl_from!(ParenExpr, ParenthesisExpression);
+impl_from!(OptChainExpr, OptionalChainingExpression);
+impl_from!(SwitchCase, SwitchCase);
+
+impl_from!(OptCall, OptionalCallExpression);
 
 
 // literals
-impl_from!(ObjectLit, "ObjectExpression");
-impl_from!(Str, "StringLiteral");
-impl_from!(Bool, "BooleanLiteral");
-impl_from!(Null, "NullLiteral");
-impl_from!(Number, "NumericLiteral");
-impl_from!(BigInt, "BigIntLiteral");
-impl_from!(Regex, "RegExpLiteral");
-impl_from!(Tpl, "TemplateLiteral");
-
-
-impl_from!(TaggedTpl, "TaggedTemplateExpression");
-impl_from!(ThisExpr, "ThisExpression");
-impl_from!(UnaryExpr, "UnaryExpression");
-impl_from!(ClassMethod, "ClassMethod");
-impl_from!(ClassMember, "ClassMember");
-impl_from!(ComputedPropName, "Computed");
-impl_from!(ClassProp, "ClassProp");
-impl_from!(Constructor, "Constructor");
-impl_from!(Decorator, "Decorator");
-
-impl_from!(ExportDecl, "ExportDeclaration");
-impl_from!(ExportAll, "ExportAllDeclaration");
-impl_from!(ExportDefaultDecl, "ExportDefaultDeclaration");
-impl_from!(ExportNamedSpecifier, "ExportSpecifier");
-impl_from!(ExportDefaultSpecifier, "ExportDefaultSpecifier");
-impl_from!(ExportNamespaceSpecifier, "ExportNamespaceSpecifier");
-impl_from!(NamedExport, "ExportNamedDeclaration");
-
-impl_from!(VarDecl, "VariableDeclaration");
-// impl_from_option_boxed!(Expr, "VariableDeclaration");
-impl_from!(ClassDecl, "ClassDeclaration");
-impl_from!(FnDecl, "FunctionDeclaration");
-impl_from!(Param,"Parameter");
-impl_from!(VarDeclarator, "VariableDeclarator");
-impl_from!(BindingIdent, "Identifier");
-
-
-impl_from!(ArrayPat, "ArrayPattern");
-impl_from!(RestPat, "RestElement");
-impl_from!(ObjectPat, "ObjectPattern");
-impl_from!(AssignPat, "AssignPattern");
-impl_from!(TsEnumMember, "TsEnumMember");
-impl_from!(TsEnumDecl, "TsEnumDeclaration");
-impl_from!(KeyValueProp, "KeyValueProperty");
-impl_from!(KeyValuePatProp, "KeyValuePatternProperty");
-impl_from!(ExprStmt, "ExpressionStatement");
-
-impl_from!(Import, "Import");
-impl_from!(ImportDecl, "Import");
-impl_from!(ImportStarAsSpecifier, "ImportNamespaceSpecifier");
-impl_from!(ImportNamedSpecifier, "ImportSpecifier");
-impl_from!(ImportDefaultSpecifier, "ImportDefaultSpecifier");
-
-
-
-impl_from_boxed!(VarDecl, "VariableDeclaration");
-impl_from_boxed!(TryStmt, "TryStatement");
-impl_from_boxed!(TsEnumDecl, "TsEnumDeclaration");
+impl_from!(ObjectLit, ObjectExpression);
+impl_from!(Str, StringLiteral);
+impl_from!(Bool, BooleanLiteral);
+impl_from!(Null, NullLiteral);
+impl_from!(Number, NumericLiteral);
+impl_from!(BigInt, BigIntLiteral);
+impl_from!(Regex, RegExpLiteral);
+impl_from!(Tpl, TemplateLiteral);
+
+
+impl_from!(TaggedTpl, TaggedTemplateExpression);
+impl_from!(ThisExpr, ThisExpression);
+impl_from!(UnaryExpr, UnaryExpression);
+impl_from!(ClassMethod, ClassMethod);
+impl_from!(ClassMember, ClassMember);
+impl_from!(ComputedPropName, ComputedPropertyName);
+impl_from!(ClassProp, ClassProp);
+impl_from!(Constructor, Constructor);
+impl_from!(Decorator, Decorator);
+
+impl_from!(ExportDecl, ExportDeclaration, |n| decl_name(&n.decl));
+impl_from!(ExportAll, ExportAllDeclaration);
+impl_from!(ExportDefaultDecl, ExportDefaultDeclaration, export_default_decl_name);
+impl_from!(ExportNamedSpecifier, ExportSpecifier);
+impl_from!(ExportDefaultSpecifier, ExportDefaultSpecifier);
+impl_from!(ExportNamespaceSpecifier, ExportNamespaceSpecifier);
+impl_from!(NamedExport, ExportNamedDeclaration);
+
+impl_from!(VarDecl, VariableDeclaration, var_decl_name);
+impl_from!(ClassDecl, ClassDeclaration, |n| ident_name(&n.ident));
+impl_from!(FnDecl, FunctionDeclaration, |n| ident_name(&n.ident));
+impl_from!(Param, Parameter);
+impl_from!(VarDeclarator, VariableDeclarator, |n| pat_name(&n.name));
+impl_from!(BindingIdent, Identifier);
+
+
+impl_from!(ArrayPat, ArrayPattern);
+impl_from!(RestPat, RestElement);
+impl_from!(ObjectPat, ObjectPattern);
+impl_from!(AssignPat, AssignPattern);
+impl_from!(TsEnumMember, TsEnumMember);
+impl_from!(TsEnumDecl, TsEnumDeclaration, |n| ident_name(&n.id));
+impl_from!(KeyValueProp, KeyValueProperty);
+impl_from!(KeyValuePatProp, KeyValuePatternProperty);
+impl_from!(ExprStmt, ExpressionStatement);
+
+impl_from!(Import, Import);
+impl_from!(ImportDecl, ImportDeclaration);
+impl_from!(ImportStarAsSpecifier, ImportNamespaceSpecifier, |n| ident_name(&n.local));
+impl_from!(ImportNamedSpecifier, ImportSpecifier, |n| ident_name(&n.local));
+impl_from!(ImportDefaultSpecifier, ImportDefaultSpecifier, |n| ident_name(&n.local));
+
+impl_from!(JSXElement, JSXElement);
+impl_from!(JSXFragment, JSXFragment);
+impl_from!(JSXAttr, JSXAttribute);
+impl_from!(JSXExprContainer, JSXExpressionContainer);
+impl_from!(JSXMemberExpr, JSXMemberExpression);
+
+
+
+impl_from_boxed!(VarDecl, VariableDeclaration, var_decl_name);
+impl_from_boxed!(TryStmt, TryStatement);
+impl_from_boxed!(TsEnumDecl, TsEnumDeclaration, |n| ident_name(&n.id));
 
 
 // impl From<&mut Option<Box<Expr>>> for Neighbor {
@@ -249,12 +520,10 @@ macro_rules! add_and_descend {
     [$( $Foo:path ),*]
     ) => {
         match $x {
-            $($Foo(a) => {
+            $($Foo(a) if $self.is_in(a.span()) => {
             // println!("adding and descending {:?}", a.clone());
-                if $self.is_in(a.span()) {
-                   $self.neighbors.push(a.into());
-                   a.visit_mut_children_with($self)
-                }
+                $self.neighbors.push(a.into());
+                a.visit_mut_children_with($self)
             },)*
             _ => {},
         }
@@ -312,14 +581,6 @@ macro_rules! add_and_visit_if_in_span {
         }
     }
 }
-macro_rules! when_some_visit_all_children_with_self {
-    ($self:ident, $a:ident) => {
-        match $a {
-            None => {},
-            Some(x) => x.visit_mut_children_with($self)
-        }
-    }
-}
 
 impl VisitMut for FindNeighbors {
     fn visit_mut_module_item(&mut self, x: &mut ModuleItem) {
@@ -337,7 +598,7 @@ impl VisitMut for FindNeighbors {
     }
 
 
-    /// enum / union types
+    // enum / union types
     fn visit_mut_expr(&mut self, n: &mut Expr) {
         // println!("visit expr, {}, {}, \n{:?}", n.span_lo().0, n.span_hi().0, serde_json::to_string(n).unwrap());
         match n {
@@ -356,6 +617,19 @@ impl VisitMut for FindNeighbors {
                 l.visit_mut_with(self);
                 return;
             }
+            // nested elements are not expressions, so these are handled by the jsx visitors below
+            Expr::JSXElement(l) => {
+                l.visit_mut_with(self);
+                return;
+            }
+            Expr::JSXFragment(l) => {
+                l.visit_mut_with(self);
+                return;
+            }
+            Expr::JSXMember(l) => {
+                l.visit_mut_with(self);
+                return;
+            }
 
             _ => {}
         };
@@ -396,11 +670,8 @@ impl VisitMut for FindNeighbors {
 
             // not visiting
             // Expr::PrivateName,
-            // Expr::JSXMember
             // Expr::JSXNamespacedName
             // Expr::JSXEmpty
-            // Expr::JSXElement
-            // Expr::JSXFragment
             // Expr::TsTypeAssertion
             // Expr::TsConstAssertion
             // Expr::TsNonNull
@@ -525,8 +796,8 @@ impl VisitMut for FindNeighbors {
     // the ident span range includes the range for type annotations so remove those
     fn visit_mut_binding_ident(&mut self, n: &mut BindingIdent) {
         match &n.type_ann {
-            Some(x) => {
-                let ident_end = n.type_ann.as_ref().unwrap().span.lo.0 ;
+            Some(type_ann) => {
+                let ident_end = type_ann.span.lo.0;
                 let ident_start = n.id.span.lo.0;
                 // is target in span?
                 if self.target_loc >= ident_start && self.target_loc <= ident_end {
@@ -563,23 +834,22 @@ impl VisitMut for FindNeighbors {
 
     // fn visit_mut_empty_stmt(&mut self, n: &mut EmptyStmt) { add_if_in_span!(self, n); }
 
-    /// loops
+    // loops
     fn visit_mut_while_stmt(&mut self, n: &mut WhileStmt) { add_and_visit_if_in_span!(self, n); }
     fn visit_mut_do_while_stmt(&mut self, n: &mut DoWhileStmt) { add_and_visit_if_in_span!(self, n); }
     fn visit_mut_for_in_stmt(&mut self, n: &mut ForInStmt) { add_and_visit_if_in_span!(self, n); }
     fn visit_mut_for_of_stmt(&mut self, n: &mut ForOfStmt) { add_and_visit_if_in_span!(self, n); }
     fn visit_mut_for_stmt(&mut self, n: &mut ForStmt) { add_and_visit_if_in_span!(self, n); }
 
-    /// functions
+    // functions
     fn visit_mut_fn_decl(&mut self, n: &mut FnDecl) { add_and_visit_if_in_span!(self, n); }
     fn visit_mut_fn_expr(&mut self, n: &mut FnExpr) { add_and_visit_if_in_span!(self, n); }
     fn visit_mut_function(&mut self, n: &mut Function) {
         add_if_in_span!(self, n);
         n.params.visit_mut_with(self);
-        match &mut n.body {
-            Some(x) => { add_and_visit_if_in_span!(self, x); }
-            None => {}
-        };
+        if let Some(x) = &mut n.body {
+            add_and_visit_if_in_span!(self, x);
+        }
         // n.return_type
         // n.type_params
     }
@@ -595,7 +865,7 @@ impl VisitMut for FindNeighbors {
     fn visit_mut_ident(&mut self, n: &mut Ident) { add_if_in_span!(self, n); }
 
 
-    /// control flow
+    // control flow
     fn visit_mut_if_stmt(&mut self, n: &mut IfStmt) { add_if_in_span!(self, n); }
     fn visit_mut_switch_case(&mut self, n: &mut SwitchCase) { add_and_visit_if_in_span!(self, n); }
     // fn visit_mut_switch_cases(&mut self, n: &mut Vec<SwitchCase>) { add_if_in_span!(self, n); }
@@ -637,7 +907,7 @@ impl VisitMut for FindNeighbors {
     // fn visit_mut_object_pat_prop(&mut self, n: &mut ObjectPatProp) { add_and_visit_if_in_span!(self, n); }
     // fn visit_mut_object_pat_props(&mut self, n: &mut Vec<ObjectPatProp>) { add_if_in_span!(self, n); }
 
-    /// literals
+    // literals
     fn visit_mut_lit(&mut self, n: &mut Lit) {
         add_and_descend!(self, n, [
             Lit::Str,
@@ -676,18 +946,9 @@ impl VisitMut for FindNeighbors {
     fn visit_mut_paren_expr(&mut self, n: &mut ParenExpr) { add_and_visit_if_in_span!(self, n); }
 
     fn visit_mut_pat(&mut self, n: &mut Pat) {
-        match n {
-            Pat::Ident(i) => {
-                // force visit binding ident for special handling of span that includes type annotation
-                i.visit_mut_with(self)
-            }
-            _ => {}
-            // Pat::Array(_) => {}
-            // Pat::Rest(_) => {}
-            // Pat::Object(_) => {}
-            // Pat::Assign(_) => {}
-            // Pat::Invalid(_) => {}
-            // Pat::Expr(_) => {}
+        if let Pat::Ident(i) = n {
+            // force visit binding ident for special handling of span that includes type annotation
+            i.visit_mut_with(self)
         }
         add_and_descend!(self, n, [
             // handled above
@@ -772,7 +1033,7 @@ impl VisitMut for FindNeighbors {
     // fn visit_mut_static_block(&mut self, n: &mut StaticBlock) { add_if_in_span!(self, n); }
 
 
-    /// classes / oo
+    // classes / oo
     fn visit_mut_this_expr(&mut self, n: &mut ThisExpr) { add_if_in_span!(self, n); }
     // fn visit_mut_super(&mut self, n: &mut Super) { add_if_in_span!(self, n); }
     // fn visit_mut_super_prop(&mut self, n: &mut SuperProp) { add_if_in_span!(self, n); }
@@ -794,7 +1055,7 @@ impl VisitMut for FindNeighbors {
 
     fn visit_mut_try_stmt(&mut self, n: &mut TryStmt) { add_and_visit_if_in_span!(self, n); }
 
-    /// ops
+    // ops
     // fn visit_mut_true_plus_minus(&mut self, n: &mut TruePlusMinus) { add_if_in_span!(self, n); }
     // fn visit_mut_unary_expr(&mut self, n: &mut UnaryExpr) { add_if_in_span!(self, n); }
     // fn visit_mut_unary_op(&mut self, n: &mut UnaryOp) { add_if_in_span!(self, n); }
@@ -820,13 +1081,13 @@ impl VisitMut for FindNeighbors {
     fn visit_mut_yield_expr(&mut self, n: &mut YieldExpr) { add_and_visit_if_in_span!(self, n); }
 
     /////////////////////////
-    /// swc meta
+    // swc meta
     // fn visit_mut_atom(&mut self, n: &mut Atom) { add_if_in_span!(self, n); }
     // fn visit_mut_invalid(&mut self, n: &mut Invalid) { add_if_in_span!(self, n); }
     // fn visit_mut_js_word(&mut self, n: &mut JsWord) { add_if_in_span!(self, n); }
     // fn visit_mut_span(&mut self, n: &mut Span) { add_if_in_span!(self, n); }
 
-    /// typescript
+    // typescript
     // fn visit_mut_export_namespace_specifier(&mut self, n: &mut ExportNamespaceSpecifier) { add_if_in_span!(self, n); }
     // fn visit_mut_opt_ts_entity_name(&mut self, n: &mut Option<TsEntityName>) { add_if_in_span!(self, n); }
     // fn visit_mut_opt_ts_namespace_body(&mut self, n: &mut Option<TsNamespaceBody>) { add_if_in_span!(self, n); }
@@ -914,7 +1175,7 @@ impl VisitMut for FindNeighbors {
     // fn visit_mut_ts_union_or_intersection_type(&mut self, n: &mut TsUnionOrIntersectionType) { add_if_in_span!(self, n); }
     // fn visit_mut_ts_union_type(&mut self, n: &mut TsUnionType) { add_if_in_span!(self, n); }
 
-    /// optional types
+    // optional types
     // fn visit_mut_opt_accessibility(&mut self, n: &mut Option<Accessibility>) { add_if_in_span!(self, n); }
     // fn visit_mut_opt_atom(&mut self, n: &mut Option<Atom>) { add_if_in_span!(self, n); }
     fn visit_mut_opt_block_stmt(&mut self, n: &mut Option<BlockStmt>) {
@@ -953,22 +1214,34 @@ impl VisitMut for FindNeighbors {
     // fn visit_mut_opt_vec_pats(&mut self, n: &mut Vec<Option<Pat>>) { add_if_in_span!(self, n); }
 
     // jsx
-    // fn visit_mut_jsx_attr(&mut self, n: &mut JSXAttr) { add_if_in_span!(self, n); }
+    fn visit_mut_jsx_attr(&mut self, n: &mut JSXAttr) {
+        // the name is a prop, not a binding
+        if self.is_in(n.span()) {
+            self.neighbors.push(n.into());
+            n.value.visit_mut_with(self);
+        }
+    }
     // fn visit_mut_jsx_attr_name(&mut self, n: &mut JSXAttrName) { add_if_in_span!(self, n); }
     // fn visit_mut_jsx_attr_or_spread(&mut self, n: &mut JSXAttrOrSpread) { add_if_in_span!(self, n); }
     // fn visit_mut_jsx_attr_or_spreads(&mut self, n: &mut Vec<JSXAttrOrSpread>) { add_if_in_span!(self, n); }
     // fn visit_mut_jsx_attr_value(&mut self, n: &mut JSXAttrValue) { add_if_in_span!(self, n); }
     // fn visit_mut_jsx_closing_element(&mut self, n: &mut JSXClosingElement) { add_if_in_span!(self, n); }
     // fn visit_mut_jsx_closing_fragment(&mut self, n: &mut JSXClosingFragment) { add_if_in_span!(self, n); }
-    // fn visit_mut_jsx_element(&mut self, n: &mut JSXElement) { add_if_in_span!(self, n); }
+    fn visit_mut_jsx_element(&mut self, n: &mut JSXElement) { add_and_visit_if_in_span!(self, n); }
     // fn visit_mut_jsx_element_child(&mut self, n: &mut JSXElementChild) { add_if_in_span!(self, n); }
     // fn visit_mut_jsx_element_children(&mut self, n: &mut Vec<JSXElementChild>) { add_if_in_span!(self, n); }
-    // fn visit_mut_jsx_element_name(&mut self, n: &mut JSXElementName) { add_if_in_span!(self, n); }
+    fn visit_mut_jsx_element_name(&mut self, n: &mut JSXElementName) {
+        match n {
+            // lowercase names are intrinsic elements like div, not bindings
+            JSXElementName::Ident(i) if i.sym.starts_with(|c: char| c.is_ascii_lowercase()) => {}
+            _ => n.visit_mut_children_with(self),
+        }
+    }
     // fn visit_mut_jsx_empty_expr(&mut self, n: &mut JSXEmptyExpr) { add_if_in_span!(self, n); }
     // fn visit_mut_jsx_expr(&mut self, n: &mut JSXExpr) { add_if_in_span!(self, n); }
-    // fn visit_mut_jsx_expr_container(&mut self, n: &mut JSXExprContainer) { add_if_in_span!(self, n); }
-    // fn visit_mut_jsx_fragment(&mut self, n: &mut JSXFragment) { add_if_in_span!(self, n); }
-    // fn visit_mut_jsx_member_expr(&mut self, n: &mut JSXMemberExpr) { add_if_in_span!(self, n); }
+    fn visit_mut_jsx_expr_container(&mut self, n: &mut JSXExprContainer) { add_and_visit_if_in_span!(self, n); }
+    fn visit_mut_jsx_fragment(&mut self, n: &mut JSXFragment) { add_and_visit_if_in_span!(self, n); }
+    fn visit_mut_jsx_member_expr(&mut self, n: &mut JSXMemberExpr) { add_if_in_span!(self, n); }
     // fn visit_mut_jsx_namespaced_name(&mut self, n: &mut JSXNamespacedName) { add_if_in_span!(self, n); }
     // fn visit_mut_jsx_object(&mut self, n: &mut JSXObject) { add_if_in_span!(self, n); }
     // fn visit_mut_jsx_opening_element(&mut self, n: &mut JSXOpeningElement) { add_if_in_span!(self, n); }
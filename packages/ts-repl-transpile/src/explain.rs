@@ -0,0 +1,102 @@
+use crate::error::{Error, Result};
+use crate::options::{self, TransformOptions};
+use crate::pipeline::Pipeline;
+use crate::TransformOutput;
+use serde::Serialize;
+use std::time::Instant;
+use swc_core::common::comments::SingleThreadedComments;
+
+// Shows what each stage of the REPL transform did to a snippet, for when the
+// output misbehaves and it isn't clear which pass is to blame.
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+pub struct TransformStage {
+  /// "parse", "plugins" or the name of a pass
+  pub name: String,
+  /// The module after this stage, emitted without comments
+  pub code: String,
+  /// swc's JSON AST after this stage, when asked for
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub ast: Option<String>,
+  /// Time spent in the stage, not counting the emit for `code`
+  pub duration_ms: f64,
+  /// Whether `code` differs from the stage before
+  pub changed: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+pub struct ExplainedTransform {
+  pub stages: Vec<TransformStage>,
+  /// What `transformSync` returns for the same source and options
+  pub output: TransformOutput,
+  pub total_ms: f64,
+}
+
+fn millis(since: Instant) -> f64 {
+  since.elapsed().as_secs_f64() * 1000.0
+}
+
+/// Runs the default pipeline over `source`, keeping the code after every
+/// stage. The AST is included as JSON when `include_ast` is set.
+pub fn explain_transform(
+  source: String,
+  options: Option<TransformOptions>,
+  include_ast: bool,
+) -> Result<ExplainedTransform> {
+  let opts = options::resolve(options)?;
+  let stage_opts = options::ResolvedOptions {
+    source_maps: false,
+    ..opts.clone()
+  };
+  let mut stages: Vec<TransformStage> = vec![];
+
+  let started = Instant::now();
+  let mut last = started;
+  let mut excluded = 0.0;
+  let (output, _) = crate::transform_repl(
+    source,
+    &opts,
+    None,
+    &Pipeline::default(),
+    &mut |name, module, ctx| {
+      let duration_ms = millis(last);
+      let observed = Instant::now();
+      // emitting with the real comments would use them up
+      let (code, _) = crate::emit(
+        ctx.cm,
+        module,
+        &SingleThreadedComments::default(),
+        &stage_opts,
+      )?;
+      let ast = if include_ast {
+        Some(
+          serde_json::to_string(module)
+            .map_err(|err| Error::failure(format!("Could not serialize the module: {}", err)))?,
+        )
+      } else {
+        None
+      };
+      let changed = stages.last().is_none_or(|stage| stage.code != code);
+      stages.push(TransformStage {
+        name: name.into(),
+        code,
+        ast,
+        duration_ms,
+        changed,
+      });
+      excluded += millis(observed);
+      last = Instant::now();
+      Ok(())
+    },
+  )?;
+
+  Ok(ExplainedTransform {
+    stages,
+    output,
+    total_ms: millis(started) - excluded,
+  })
+}
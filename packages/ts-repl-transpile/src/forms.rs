@@ -0,0 +1,229 @@
+//! Structural selection over the forms `evaluable_spans` finds: what to grow
+//! or shrink a selection to, the forms to evaluate for "last form" and
+//! "top-level form" style commands, and the forms that cover a selection.
+
+use crate::diagnostics::Diagnostic;
+use crate::document::Document;
+use crate::error::Result;
+use crate::evaluable_spans::{Neighbor, Target};
+use crate::line_index::Position;
+use crate::options::TransformOptions;
+use serde::Serialize;
+
+/// A cursor, when `start` and `end` are the same, or the selection between
+/// them, in either order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Selection {
+  pub start: Target,
+  pub end: Target,
+}
+
+impl From<Target> for Selection {
+  fn from(target: Target) -> Self {
+    Selection {
+      start: target,
+      end: target,
+    }
+  }
+}
+
+impl From<u32> for Selection {
+  fn from(offset: u32) -> Self {
+    Target::from(offset).into()
+  }
+}
+
+impl From<Position> for Selection {
+  fn from(position: Position) -> Self {
+    Target::from(position).into()
+  }
+}
+
+impl<T: Into<Target>> From<(T, T)> for Selection {
+  fn from((start, end): (T, T)) -> Self {
+    Selection {
+      start: start.into(),
+      end: end.into(),
+    }
+  }
+}
+
+/// The forms around a selection. `spans` is what `evaluableSpans` returns
+/// for its first character, or the character before a cursor, and the rest
+/// are indexes into it.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+pub struct SelectedForms {
+  pub spans: Vec<Neighbor>,
+  /// The smallest form that holds more than the selection. For a cursor,
+  /// the innermost form before it.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub expand: Option<u32>,
+  /// The largest form inside the selection that is smaller than it, which
+  /// starts where it does. Not set for a cursor.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub shrink: Option<u32>,
+  /// The statement or declaration at the top of the module that holds the
+  /// selection
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub top_level: Option<u32>,
+  /// The innermost expression that holds the selection
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub innermost_expression: Option<u32>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub diagnostics: Option<Vec<Diagnostic>>,
+}
+
+/// Finds the forms to expand `selection` to or shrink it to, and the
+/// top-level form and innermost expression holding it
+pub fn select_forms(
+  source: String,
+  selection: impl Into<Selection>,
+  options: Option<TransformOptions>,
+) -> Result<SelectedForms> {
+  let selection = selection.into();
+  let mut document = Document::parse(source, options)?;
+  let start = document.boundary(selection.start)?;
+  let end = document.boundary(selection.end)?;
+  let (start, end) = (start.min(end), start.max(end));
+
+  let cursor = start == end;
+  let mut spans = if cursor {
+    document.neighbors(document.cursor(selection.start)?)
+  } else {
+    document.neighbors(start)
+  };
+  // a cursor is in every span found for it
+  let holds = |span: &Neighbor| cursor || (span.start <= start && end <= span.end);
+  let is_selection = |span: &Neighbor| span.start == start && span.end == end;
+
+  let expand = spans
+    .iter()
+    .rposition(|span| holds(span) && !is_selection(span));
+  let shrink = spans
+    .iter()
+    .position(|span| !cursor && start <= span.start && span.end <= end && !is_selection(span));
+  // spans are found from the top of the module down
+  let top_level = spans.first().filter(|span| holds(span)).map(|_| 0);
+  let innermost_expression = spans
+    .iter()
+    .rposition(|span| span.r#type.is_expression() && holds(span));
+
+  document.encode(&mut spans);
+  let index = |i: Option<usize>| i.map(|i| i as u32);
+  Ok(SelectedForms {
+    spans,
+    expand: index(expand),
+    shrink: index(shrink),
+    top_level: index(top_level),
+    innermost_expression: index(innermost_expression),
+    diagnostics: document.take_diagnostics(),
+  })
+}
+
+/// The forms to evaluate for one selection
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+pub struct CoveringForms {
+  /// The fewest complete forms that cover the selection, in order, without
+  /// `parent`s. For a cursor, the innermost form before it.
+  pub forms: Vec<Neighbor>,
+  /// Whether the selection starts or ends inside one of `forms`, so that
+  /// evaluating it as it is would cut through a form
+  pub cuts_form: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+pub struct EvaluableSelections {
+  /// The forms for each selection, in the order they were given
+  pub selections: Vec<CoveringForms>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub diagnostics: Option<Vec<Diagnostic>>,
+}
+
+/// For each of `selections`, like the ones of a multi-cursor editor, finds
+/// the complete forms that cover it, so that evaluating a selection can snap
+/// to code that makes sense on its own
+pub fn evaluable_selections(
+  source: String,
+  selections: Vec<Selection>,
+  options: Option<TransformOptions>,
+) -> Result<EvaluableSelections> {
+  let mut document = Document::parse(source, options)?;
+  let mut covered = Vec::with_capacity(selections.len());
+  for selection in selections {
+    let mut forms = covering_forms(&mut document, selection)?;
+    document.encode(&mut forms.forms);
+    covered.push(forms);
+  }
+  Ok(EvaluableSelections {
+    selections: covered,
+    diagnostics: document.take_diagnostics(),
+  })
+}
+
+fn covering_forms(document: &mut Document, selection: Selection) -> Result<CoveringForms> {
+  let start = document.boundary(selection.start)?;
+  let end = document.boundary(selection.end)?;
+  if start == end {
+    let mut spans = document.neighbors(document.cursor(selection.start)?);
+    return Ok(covering(spans.pop().into_iter().collect(), false));
+  }
+  let (start, end) = document.trim(start.min(end), start.max(end));
+  if start == end {
+    return Ok(covering(vec![], false));
+  }
+
+  // the innermost form holding the whole selection, whose children between
+  // its ends are the forms, or the module when there is none
+  let mut spans = document.neighbors(start);
+  let parent = spans
+    .iter()
+    .rposition(|span| span.start <= start && end <= span.end);
+  if let Some(parent) = parent {
+    if spans[parent].start == start && spans[parent].end == end {
+      return Ok(covering(vec![spans.swap_remove(parent)], false));
+    }
+  }
+  let depth = parent.map_or(0, |parent| spans[parent].depth + 1);
+
+  let mut forms: Vec<Neighbor> = vec![];
+  let mut pos = start;
+  while pos < end {
+    // the spans around any position in the parent start with the same ones
+    let mut around = document.neighbors(pos);
+    pos = match around.iter().position(|span| span.depth == depth) {
+      Some(child) => {
+        let child = around.swap_remove(child);
+        let next = child.end.max(pos + 1);
+        forms.push(child);
+        next
+      }
+      // between children, like the operator of a binary expression or a
+      // comment, straight to where the next node starts
+      None => document.next_node(pos, end),
+    };
+    pos = document.skip_whitespace(pos);
+  }
+
+  if forms.is_empty() {
+    return Ok(match parent {
+      // only what is between the parent's children, so it takes all of it
+      Some(parent) => covering(vec![spans.swap_remove(parent)], true),
+      None => covering(vec![], false),
+    });
+  }
+  let cuts_form = forms[0].start < start || end < forms[forms.len() - 1].end;
+  Ok(covering(forms, cuts_form))
+}
+
+fn covering(mut forms: Vec<Neighbor>, cuts_form: bool) -> CoveringForms {
+  for form in &mut forms {
+    form.parent = None;
+  }
+  CoveringForms { forms, cuts_form }
+}
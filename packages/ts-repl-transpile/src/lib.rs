@@ -1,47 +1,75 @@
 #![deny(clippy::all)]
 
-mod evaluable_spans;
-mod rewrite_all_to_var;
-mod tla;
-
+pub mod ast_hook;
+#[cfg(feature = "napi")]
+mod async_tasks;
+#[cfg(feature = "napi")]
+mod bindings;
+pub mod diagnostics;
+mod document;
+pub mod error;
+mod esm;
+pub mod evaluable_spans;
+pub mod explain;
+pub mod forms;
+pub mod line_index;
+pub mod logging;
+pub mod options;
+pub mod panic;
+pub mod pipeline;
+mod plugins;
+pub mod session;
+mod source_map;
+pub mod stack_trace;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+#[cfg(feature = "napi")]
 #[macro_use]
 extern crate napi_derive;
 
-use crate::evaluable_spans::{FindNeighbors, Neighbor};
-use crate::rewrite_all_to_var::TransformAllToVar;
-use crate::tla::transform_top_level_await;
-use napi::Status;
+pub use crate::diagnostics::{Diagnostic, DiagnosticSpan};
+use crate::document::Document;
+pub use crate::evaluable_spans::{Neighbor, NodeKind, Target};
+pub use crate::forms::{
+  evaluable_selections, select_forms, CoveringForms, EvaluableSelections, SelectedForms, Selection,
+};
+pub use crate::line_index::{Position, Range};
+use crate::options::{DecoratorsMode, ModuleType, ResolvedOptions};
+pub use crate::options::{JsxOptions, ModuleOptions, PluginOptions, TransformOptions};
+pub use crate::pipeline::{Observer, PassContext, Pipeline, ReplPass};
+use crate::session::{NamespaceState, TransformReport};
+pub use crate::error::{Error, ErrorKind, Result};
+pub use crate::explain::explain_transform;
+pub use crate::session::ReplSession;
+pub use crate::stack_trace::map_stack_trace;
+pub use swc_plugin_typescript_repl::{rewrite_all_to_var, tla};
+// passes are written against this crate's swc
+pub use swc_core;
 use serde::Serialize;
 use std::borrow::Borrow;
 use std::sync::Arc;
-use swc_core::base::Compiler;
-use swc_core::common::comments::{NoopComments, SingleThreadedComments};
-use swc_core::common::errors::{ColorConfig, Handler};
+use swc_core::common::comments::SingleThreadedComments;
+use swc_core::common::errors::HANDLER;
 use swc_core::common::input::SourceFileInput;
-use swc_core::common::sync::Lazy;
-use swc_core::common::{FileName, FilePathMapping, Globals, SourceMap};
+use swc_core::common::util::take::Take;
+use swc_core::common::{Globals, SourceMap};
 use swc_core::common::{Mark, GLOBALS};
-use swc_core::ecma::ast::EsVersion;
+use swc_core::ecma::ast::Module;
 use swc_core::ecma::codegen::text_writer::JsWriter;
 use swc_core::ecma::codegen::Emitter;
 use swc_core::ecma::parser::lexer::Lexer;
-use swc_core::ecma::parser::{Parser, Syntax, TsConfig};
+use swc_core::ecma::parser::Parser;
 use swc_core::ecma::transforms::base::feature::enable_available_feature_from_es_version;
-use swc_core::ecma::transforms::base::helpers::HELPERS;
-use swc_core::ecma::transforms::module::common_js::Config as CommonJSConfig;
-use swc_core::ecma::transforms::module::util::ImportInterop;
+use swc_core::ecma::transforms::base::helpers::{inject_helpers, HELPERS};
+use swc_core::ecma::transforms::base::resolver;
 use swc_core::ecma::transforms::module::*;
+use swc_core::ecma::transforms::proposal::decorators::{decorators, Config as DecoratorsConfig};
+use swc_core::ecma::transforms::react::{react, Runtime};
 use swc_core::ecma::transforms::typescript::{
-  strip_with_config, Config as TSTransformConfig, ImportsNotUsedAsValues,
+  strip_with_config, strip_with_jsx, Config as TSTransformConfig, ImportsNotUsedAsValues,
 };
 use swc_core::ecma::visit::*;
-use swc_plugin_typescript_repl::TransformImportNamedToDestructuringRequireVisitor;
-
-// Copied from swc
-static COMPILER: Lazy<Arc<Compiler>> = Lazy::new(|| {
-  let cm = Arc::new(SourceMap::new(FilePathMapping::empty()));
-  Arc::new(Compiler::new(cm))
-});
 
 const EXPORT_STAR_HELPER: &str = r#"function _exportStar(from, to) {
     Object.keys(from).forEach(function(k) {
@@ -58,101 +86,204 @@ const EXPORT_STAR_HELPER: &str = r#"function _exportStar(from, to) {
     return from;
 }"#;
 
-// Copied from swc
-fn get_compiler() -> Arc<Compiler> {
-  COMPILER.clone()
+/// Lowers decorators when asked to. Must run before the typescript strip so
+/// metadata can still see type annotations, and inside `HELPERS` since the
+/// lowered code calls swc helpers (inlined by `inject_helpers` afterwards).
+fn apply_decorators(module: Module, opts: &ResolvedOptions) -> Module {
+  match opts.decorators {
+    DecoratorsMode::Legacy | DecoratorsMode::Proposal => {
+      module.fold_with(&mut decorators(DecoratorsConfig {
+        legacy: opts.decorators == DecoratorsMode::Legacy,
+        emit_metadata: opts.decorator_metadata,
+        ..Default::default()
+      }))
+    }
+    DecoratorsMode::None | DecoratorsMode::Preserve => module,
+  }
+}
+
+/// Strips types. With JSX in the classic runtime, what the pragma names counts
+/// as used, so the `React` import JSX needs isn't dropped with the unused ones.
+fn strip_types(
+  module: Module,
+  cm: &Arc<SourceMap>,
+  comments: &SingleThreadedComments,
+  opts: &ResolvedOptions,
+  top_level_mark: Mark,
+) -> Module {
+  let config = TSTransformConfig {
+    import_not_used_as_values: ImportsNotUsedAsValues::Preserve,
+    ..Default::default()
+  };
+  if !opts.lowers_jsx() || opts.react.runtime == Some(Runtime::Automatic) {
+    return module.fold_with(&mut strip_with_config(config, top_level_mark));
+  }
+  module.fold_with(&mut strip_with_jsx(
+    cm.clone(),
+    TSTransformConfig {
+      pragma: opts.react.pragma.clone(),
+      pragma_frag: opts.react.pragma_frag.clone(),
+      ..config
+    },
+    comments.clone(),
+    top_level_mark,
+  ))
+}
+
+/// Lowers JSX when the syntax allows it. Runs after the typescript strip and
+/// before `common_js`, so the automatic runtime's jsx-runtime import is turned
+/// into a `require` like any other import.
+fn apply_react(
+  module: Module,
+  cm: &Arc<SourceMap>,
+  comments: &SingleThreadedComments,
+  opts: &ResolvedOptions,
+  top_level_mark: Mark,
+) -> Module {
+  if !opts.lowers_jsx() {
+    return module;
+  }
+  module.fold_with(&mut react(
+    cm.clone(),
+    Some(comments.clone()),
+    opts.react.clone(),
+    top_level_mark,
+  ))
+}
+
+/// Emits `module`, along with a source map back into the original document
+/// when one was asked for
+fn emit(
+  cm: &Arc<SourceMap>,
+  module: &Module,
+  comments: &SingleThreadedComments,
+  opts: &ResolvedOptions,
+) -> Result<(String, Option<String>)> {
+  let mut buf = vec![];
+  let mut src_map_buf = vec![];
+  {
+    let mut emitter = Emitter {
+      cfg: Default::default(),
+      cm: cm.clone(),
+      comments: Some(comments),
+      wr: JsWriter::new(
+        cm.clone(),
+        "\n",
+        &mut buf,
+        if opts.source_maps {
+          Some(&mut src_map_buf)
+        } else {
+          None
+        },
+      ),
+    };
+    emitter
+      .emit_module(module)
+      .map_err(|err| Error::failure(format!("{:?}", err)))?;
+  }
+
+  let map = if opts.source_maps {
+    let map = cm.build_source_map(&src_map_buf);
+    let map = source_map::offset_source_map(&map, opts.line_offset, opts.column_offset);
+    Some(
+      source_map::to_json(&map)
+        .map_err(|err| Error::failure(format!("{:?}", err)))?,
+    )
+  } else {
+    None
+  };
+
+  Ok((String::from_utf8_lossy(&buf).into(), map))
 }
 
 #[derive(Serialize)]
-#[napi_derive::napi(object)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
 pub struct TransformOutput {
   pub code: String,
   pub is_async: bool,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub map: Option<String>,
+  /// Parse and transform errors, absent when there is nothing to report.
+  /// `code` is empty when the source could not be parsed.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub diagnostics: Option<Vec<Diagnostic>>,
 }
 // Copied from swc
 #[derive(Serialize)]
-#[napi_derive::napi(object)]
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
 pub struct TransformOutputRegular {
   pub code: String,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub map: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub diagnostics: Option<Vec<Diagnostic>>,
 }
 
 #[derive(Serialize)]
-#[napi_derive::napi(object)]
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
 pub struct EvaluableSpans {
   pub spans: Vec<Neighbor>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub diagnostics: Option<Vec<Diagnostic>>,
 }
 
-#[napi]
-pub fn evaluable_spans(source: String, target: u32) -> napi::Result<EvaluableSpans> {
-  // let c = get_compiler();
-  let cm: Arc<SourceMap> = Default::default();
-
-  let output = GLOBALS.set(&Default::default(), || {
-    let handler = Handler::with_tty_emitter(ColorConfig::Auto, true, false, Some(cm.clone()));
-    let sf = cm.new_source_file(FileName::Anon, source);
-    let comments = SingleThreadedComments::default();
-
-    let lexer = Lexer::new(
-      Syntax::Typescript(TsConfig {
-        decorators: true,
-        ..Default::default()
-      }),
-      Default::default(),
-      SourceFileInput::from(sf.borrow()),
-      Some(&comments),
-    );
-
-    let mut parser = Parser::new_from(lexer);
-
-    for err in parser.take_errors() {
-      err.into_diagnostic(&handler).emit();
-    }
-
-    let module_parse = parser
-      .parse_typescript_module()
-      .map_err(|err| err.into_diagnostic(&handler).emit());
-
-    // todo. could be err, see whether other side is set up to expect that
-    if module_parse.is_err() {
-      return Ok(EvaluableSpans { spans: vec![] });
-    }
-
-    let mut module = module_parse.unwrap();
-
-    let top_level_mark = Mark::new();
-
-    let mut findn = FindNeighbors {
-      neighbors: vec![],
-      target_loc: target,
-    };
-    module.visit_mut_with(&mut findn);
-
-    Ok(EvaluableSpans {
-      spans: findn.neighbors,
-    })
-  });
-  output
+/// Finds the nodes around `target`, innermost last, that make sense to
+/// evaluate on their own. Each one's `parent` comes before it. When the
+/// source doesn't parse, its top-level statements that do are still found in.
+pub fn evaluable_spans(
+  source: String,
+  target: impl Into<Target>,
+  options: Option<TransformOptions>,
+) -> Result<EvaluableSpans> {
+  let mut document = Document::parse(source, options)?;
+  let target = document.cursor(target.into())?;
+  // no spans in the parts that do not parse, and diagnostics saying why
+  let mut spans = document.neighbors(target);
+  document.encode(&mut spans);
+  Ok(EvaluableSpans {
+    spans,
+    diagnostics: document.take_diagnostics(),
+  })
 }
 
 /// Performs a transformation on the source string such that its output
 /// is suitable for usage in a REPL environment.
-#[napi]
-pub fn transform_sync(source: String) -> napi::Result<TransformOutput> {
-  // let c = get_compiler();
-  // let cm = c.cm.clone();
+pub fn transform_sync(
+  source: String,
+  options: Option<TransformOptions>,
+) -> Result<TransformOutput> {
+  transform_with_pipeline(source, options, &Pipeline::default())
+}
+
+/// `transform_sync` with a pipeline other than the default one
+pub fn transform_with_pipeline(
+  source: String,
+  options: Option<TransformOptions>,
+  pipeline: &Pipeline,
+) -> Result<TransformOutput> {
+  let opts = options::resolve(options)?;
+  transform_repl(source, &opts, None, pipeline, &mut |_, _, _| Ok(()))
+    .map(|(output, _)| output)
+}
+
+/// Parses `source` and runs it through `pipeline`. With a namespace's name and
+/// state, values and helpers earlier evaluations left behind are reused
+/// instead of emitted again. `observer` sees the module after parsing, after
+/// plugins (when there are any) and after each pass.
+pub(crate) fn transform_repl(
+  source: String,
+  opts: &ResolvedOptions,
+  namespace: Option<(&str, &NamespaceState)>,
+  pipeline: &Pipeline,
+  observer: &mut Observer,
+) -> Result<(TransformOutput, TransformReport)> {
   let cm: Arc<SourceMap> = Default::default();
-  let handler = Handler::with_tty_emitter(ColorConfig::Auto, true, false, Some(cm.clone()));
-  let sf = cm.new_source_file(FileName::Anon, source);
+  let (handler, diagnostics) = diagnostics::handler(cm.clone());
+  let sf = cm.new_source_file(opts.file_name.clone(), source);
   let comments = SingleThreadedComments::default();
   let lexer = Lexer::new(
-    Syntax::Typescript(TsConfig {
-      decorators: true,
-      ..Default::default()
-    }),
+    opts.syntax,
     Default::default(),
     SourceFileInput::from(sf.borrow()),
     Some(&comments),
@@ -160,97 +291,93 @@ pub fn transform_sync(source: String) -> napi::Result<TransformOutput> {
 
   let mut parser = Parser::new_from(lexer);
 
-  for err in parser.take_errors() {
-    err.into_diagnostic(&handler).emit();
-  }
-
   let globals = Globals::default();
   let output = GLOBALS.set(&globals, || {
-    HELPERS.set(&Default::default(), || {
-      let top_level_mark = Mark::new();
-      let mut module = parser
-        .parse_typescript_module()
-        .map_err(|err| err.into_diagnostic(&handler).emit())
-        .expect("failed to parse module");
-
-      // todo. marks, resolver
+    HANDLER.set(&handler, || {
+      HELPERS.set(&Default::default(), || {
+        let top_level_mark = Mark::new();
+        let unresolved_mark = Mark::new();
+        let module_parse = parser
+          .parse_module()
+          .map_err(|err| err.into_diagnostic(&handler).emit());
+
+        for err in parser.take_errors() {
+          err.into_diagnostic(&handler).emit();
+        }
 
-      let mut imp_exp_visit = TransformImportNamedToDestructuringRequireVisitor {
-        ..Default::default()
-      };
-      module.visit_mut_with(&mut imp_exp_visit);
-      module = module.fold_with(&mut strip_with_config(
-        TSTransformConfig {
-          import_not_used_as_values: ImportsNotUsedAsValues::Preserve,
-          ..Default::default()
-        },
-        top_level_mark,
-      ));
-
-      // HELPERS.set(&Default::default(), || {
-      module.visit_mut_with(&mut common_js(
-        Mark::new(),
-        CommonJSConfig {
-          strict: false,
-          strict_mode: false,
-          // import_interop: Some(ImportInterop::None),
-          ..Default::default()
-        },
-        enable_available_feature_from_es_version(EsVersion::Es2016),
-        Some(&comments),
-      ));
-      // });
-
-      module.visit_mut_with(&mut TransformAllToVar);
-      let tla = transform_top_level_await(&module);
-      if tla.has_top_level_await {
-        module = tla.module.unwrap();
-      };
-
-      let mut buf = vec![];
-      let mut emitter = Emitter {
-        cfg: Default::default(),
-        cm: cm.clone(),
-        comments: Some(&comments),
-        wr: JsWriter::new(cm.clone(), "\n".into(), &mut buf, None),
-      };
-
-
-      emitter
-        .emit_module(&module)
-        .map(|_| {
-          let mut cde = String::from_utf8_lossy(&buf);
-          if imp_exp_visit.has_export_star.is_some() {
-            cde += "\n";
-            cde += EXPORT_STAR_HELPER;
+        let mut module = match module_parse {
+          Ok(module) => module,
+          Err(_) => {
+            return Ok((
+              TransformOutput {
+                code: String::new(),
+                is_async: false,
+                map: None,
+                diagnostics: diagnostics.take_option(),
+              },
+              TransformReport::default(),
+            ))
           }
+        };
+
+        let mut ctx = PassContext {
+          cm: &cm,
+          comments: &comments,
+          opts,
+          namespace,
+          top_level_mark,
+          unresolved_mark,
+          report: TransformReport::default(),
+          is_async: false,
+          has_export_star: false,
+        };
+        observer("parse", &module, &ctx)?;
+
+        // plugins see the source as written, like they do in swc, and what
+        // they declare is part of what the snippet declares
+        if !opts.plugins.is_empty() {
+          module = plugins::apply(module, &cm, &comments, opts, unresolved_mark)?;
+          observer("plugins", &module, &ctx)?;
+        }
+        ctx.report = TransformReport {
+          declared: session::top_level_bindings(&module),
+          const_enums: session::top_level_const_enums(&module),
+          helpers: vec![],
+        };
+        let module = pipeline.run_observed(module, &mut ctx, observer)?;
+        let (mut cde, map) = emit(&cm, &module, &comments, opts)?;
+        pipeline.after_emit(&mut cde, &mut ctx)?;
+        let (is_async, report) = (ctx.is_async, ctx.report);
+        Ok((
           TransformOutput {
-            code: cde.into(),
-            is_async: tla.has_top_level_await,
-            map: None,
-          }
-        })
-        .map_err(|err| napi::Error::new(Status::GenericFailure, format!("{:?}", err)))
+            code: cde,
+            is_async,
+            map,
+            diagnostics: diagnostics.take_option(),
+          },
+          report,
+        ))
+      })
     })
   });
   output
 }
 
 /// Translates TS to JS
-#[napi]
-pub fn transform_sync_regular(source: String) -> napi::Result<TransformOutputRegular> {
+pub fn transform_sync_regular(
+  source: String,
+  options: Option<TransformOptions>,
+) -> Result<TransformOutputRegular> {
+  let opts = options::resolve(options)?;
   let cm: Arc<SourceMap> = Default::default();
   let globals = Globals::new();
 
   let output = GLOBALS.set(&globals, || {
-    let handler = Handler::with_tty_emitter(ColorConfig::Auto, true, false, Some(cm.clone()));
-    let sf = cm.new_source_file(FileName::Anon, source);
+    let (handler, diagnostics) = diagnostics::handler(cm.clone());
+    let sf = cm.new_source_file(opts.file_name.clone(), source);
     let comments = SingleThreadedComments::default();
     let lexer = Lexer::new(
-      Syntax::Typescript(TsConfig {
-        decorators: true,
-        ..Default::default()
-      }),
+      opts.syntax,
       Default::default(),
       SourceFileInput::from(sf.borrow()),
       Some(&comments),
@@ -258,66 +385,66 @@ pub fn transform_sync_regular(source: String) -> napi::Result<TransformOutputReg
 
     let mut parser = Parser::new_from(lexer);
 
+    let module_parse = parser
+      .parse_module()
+      .map_err(|err| err.into_diagnostic(&handler).emit());
+
     for err in parser.take_errors() {
       err.into_diagnostic(&handler).emit();
     }
 
-    let mut module = parser
-      .parse_typescript_module()
-      .map_err(|err| err.into_diagnostic(&handler).emit())
-      .expect("failed to parse module");
+    let mut module = match module_parse {
+      Ok(module) => module,
+      Err(_) => {
+        return Ok(TransformOutputRegular {
+          code: String::new(),
+          map: None,
+          diagnostics: diagnostics.take_option(),
+        })
+      }
+    };
 
     let top_level_mark = Mark::new();
-    module = module.fold_with(&mut strip_with_config(
-      TSTransformConfig {
-        import_not_used_as_values: ImportsNotUsedAsValues::Preserve,
-        ..Default::default()
-      },
-      top_level_mark,
-    ));
-
-    module.visit_mut_with(&mut common_js(
-      Mark::new(),
-      CommonJSConfig {
-        strict: false,
-        strict_mode: false,
-        ..Default::default()
-      },
-      enable_available_feature_from_es_version(EsVersion::Es2016),
-      Some(&comments),
-    ));
+    let unresolved_mark = Mark::new();
+    HANDLER.set(&handler, || {
+      HELPERS.set(&Default::default(), || -> Result<()> {
+        module = plugins::apply(module.take(), &cm, &comments, &opts, unresolved_mark)?;
+        // `common_js` only renames the uses of an import that resolve to it,
+        // like the `React` the classic JSX runtime calls
+        module.visit_mut_with(&mut resolver(
+          unresolved_mark,
+          top_level_mark,
+          opts.syntax.typescript(),
+        ));
+        module = apply_decorators(module.take(), &opts);
+        module = strip_types(module.take(), &cm, &comments, &opts, top_level_mark);
+        module = apply_react(module.take(), &cm, &comments, &opts, top_level_mark);
+
+        if opts.module_type == ModuleType::CommonJs {
+          module.visit_mut_with(&mut common_js(
+            unresolved_mark,
+            opts.common_js.clone(),
+            enable_available_feature_from_es_version(opts.target),
+            Some(&comments),
+          ));
+        }
+        if opts.lowers_decorators() || opts.lowers_jsx() {
+          module.visit_mut_with(&mut inject_helpers());
+        }
+        Ok(())
+      })
+    })?;
 
     // let tla = transform_top_level_await(&module);
     // if tla.has_top_level_await {
     //     module = tla.module.unwrap();
     // };
 
-    let mut buf = vec![];
-    let mut emitter = Emitter {
-      cfg: Default::default(),
-      cm: cm.clone(),
-      comments: Some(&comments),
-      wr: JsWriter::new(cm.clone(), "\n".into(), &mut buf, None),
-    };
-
-    emitter
-      .emit_module(&module)
-      .map(|_| TransformOutputRegular {
-        code: String::from_utf8_lossy(&buf).into(),
-        map: None,
-      })
-      .map_err(|err| napi::Error::new(Status::GenericFailure, format!("{:?}", err)))
+    emit(&cm, &module, &comments, &opts).map(|(code, map)| TransformOutputRegular {
+      code,
+      map,
+      diagnostics: diagnostics.take_option(),
+    })
   });
   output
 }
-
-// can't run tests without a linker error?
-// #[test]
-// fn test_transform() {
-//     assert_eq!(
-//         transform_sync(
-//             "import * as foo from 'bar'".to_string()
-//         ).unwrap().code,
-//         "hello"
-//     )
-// }
@@ -0,0 +1,182 @@
+//! Converts between the byte offsets swc works in and what editors use:
+//! UTF-16 offsets and line/character positions. `\n`, `\r\n` and a lone `\r`
+//! all end a line, and a leading BOM is not part of the document, as in
+//! VS Code.
+
+use serde::{Deserialize, Serialize};
+
+/// A place in a document, like VS Code's `Position`. Both are 0-based and
+/// `character` counts UTF-16 code units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+pub struct Position {
+  pub line: u32,
+  pub character: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+pub struct Range {
+  pub start: Position,
+  pub end: Position,
+}
+
+const BOM: char = '\u{feff}';
+
+pub struct LineIndex<'a> {
+  /// The text after any BOM
+  text: &'a str,
+  /// Bytes of BOM before `text`
+  bom: u32,
+  /// Byte offset in `text` of each line's first character
+  line_starts: Vec<u32>,
+  /// UTF-16 offset of each line's first character
+  line_starts_utf16: Vec<u32>,
+}
+
+impl<'a> LineIndex<'a> {
+  pub fn new(text: &'a str) -> Self {
+    let (text, bom) = match text.strip_prefix(BOM) {
+      Some(text) => (text, BOM.len_utf8() as u32),
+      None => (text, 0),
+    };
+    let mut line_starts = vec![0];
+    let mut line_starts_utf16 = vec![0];
+    let mut utf16 = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+      utf16 += c.len_utf16() as u32;
+      let ends_line = match c {
+        '\n' => true,
+        // the `\n` of a `\r\n` ends the line instead
+        '\r' => !matches!(chars.peek(), Some((_, '\n'))),
+        _ => false,
+      };
+      if ends_line {
+        line_starts.push((i + 1) as u32);
+        line_starts_utf16.push(utf16);
+      }
+    }
+    LineIndex {
+      text,
+      bom,
+      line_starts,
+      line_starts_utf16,
+    }
+  }
+
+  /// Offset in the text given to `new` of `offset` in the document
+  pub fn with_bom(&self, offset: u32) -> u32 {
+    offset + self.bom
+  }
+
+  /// Offset in the document of `offset` in the text given to `new`, which
+  /// is 0 for offsets in the BOM
+  pub fn without_bom(&self, offset: u32) -> u32 {
+    offset.saturating_sub(self.bom)
+  }
+
+  /// The line `offset` is on
+  fn line_of(&self, offset: u32) -> usize {
+    self.line_starts.partition_point(|&start| start <= offset) - 1
+  }
+
+  /// Byte offset of the end of `line`, before its line break
+  fn line_end(&self, line: usize) -> u32 {
+    let end = match self.line_starts.get(line + 1) {
+      Some(&next) => next as usize,
+      None => return self.text.len() as u32,
+    };
+    let line_text = &self.text[self.line_starts[line] as usize..end];
+    let content = line_text
+      .strip_suffix("\r\n")
+      .or_else(|| line_text.strip_suffix('\n'))
+      .or_else(|| line_text.strip_suffix('\r'))
+      .unwrap_or(line_text);
+    self.line_starts[line] + content.len() as u32
+  }
+
+  /// The closest char boundary at or before `offset`
+  fn floor_char_boundary(&self, offset: u32) -> u32 {
+    let mut offset = (offset as usize).min(self.text.len());
+    while !self.text.is_char_boundary(offset) {
+      offset -= 1;
+    }
+    offset as u32
+  }
+
+  /// The UTF-16 offset of byte `offset`
+  pub fn utf16(&self, offset: u32) -> u32 {
+    let offset = self.floor_char_boundary(offset);
+    let line = self.line_of(offset);
+    let start = self.line_starts[line];
+    let in_line: usize = self.text[start as usize..offset as usize]
+      .chars()
+      .map(char::len_utf16)
+      .sum();
+    self.line_starts_utf16[line] + in_line as u32
+  }
+
+  pub fn position(&self, offset: u32) -> Position {
+    let offset = self.floor_char_boundary(offset);
+    let line = self.line_of(offset);
+    Position {
+      line: line as u32,
+      character: self.utf16(offset) - self.line_starts_utf16[line],
+    }
+  }
+
+  pub fn range(&self, start: u32, end: u32) -> Range {
+    Range {
+      start: self.position(start),
+      end: self.position(end),
+    }
+  }
+
+  /// The byte offset of UTF-16 offset `utf16`. Offsets past the end are
+  /// the end, and ones inside a character are its start.
+  pub fn offset_of_utf16(&self, utf16: u32) -> u32 {
+    let line = self
+      .line_starts_utf16
+      .partition_point(|&start| start <= utf16)
+      - 1;
+    self.offset_in_line(line, utf16 - self.line_starts_utf16[line])
+  }
+
+  /// The byte offset of `position`. Like VS Code's `validatePosition`,
+  /// positions past the end of their line are its end, and lines past the
+  /// last are the end of the document.
+  pub fn offset_of_position(&self, position: Position) -> u32 {
+    match self.line_starts.get(position.line as usize) {
+      Some(_) => {
+        let line = position.line as usize;
+        self
+          .offset_in_line(line, position.character)
+          .min(self.line_end(line))
+      }
+      None => self.text.len() as u32,
+    }
+  }
+
+  /// The byte offset `character` UTF-16 code units into `line`
+  fn offset_in_line(&self, line: usize, character: u32) -> u32 {
+    let start = self.line_starts[line];
+    let mut utf16 = 0;
+    for (i, c) in self.text[start as usize..].char_indices() {
+      utf16 += c.len_utf16() as u32;
+      if utf16 > character {
+        return start + i as u32;
+      }
+    }
+    self.text.len() as u32
+  }
+
+  /// The byte offset of the character before the cursor at `offset`, or of
+  /// the first character when it is at the start
+  pub fn before(&self, offset: u32) -> u32 {
+    self.text[..self.floor_char_boundary(offset) as usize]
+      .char_indices()
+      .next_back()
+      .map_or(0, |(i, _)| i as u32)
+  }
+}
@@ -0,0 +1,69 @@
+//! Sends `log` records to a callback, so an embedder like the extension can
+//! show them somewhere other than stderr.
+
+use crate::error::{Error, Result};
+use log::{LevelFilter, Log, Metadata, Record};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Once, RwLock};
+
+type Sink = Box<dyn Fn(&Record) + Send + Sync>;
+
+static SINK: RwLock<Option<Sink>> = RwLock::new(None);
+static INSTALL: Once = Once::new();
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+struct Forward;
+
+static FORWARD: Forward = Forward;
+
+impl Log for Forward {
+  fn enabled(&self, metadata: &Metadata) -> bool {
+    metadata.level() <= log::max_level()
+  }
+
+  fn log(&self, record: &Record) {
+    if !self.enabled(record.metadata()) {
+      return;
+    }
+    if let Ok(sink) = SINK.read() {
+      if let Some(sink) = sink.as_ref() {
+        sink(record);
+      }
+    }
+  }
+
+  fn flush(&self) {}
+}
+
+/// Parses one of `off`, `error`, `warn`, `info`, `debug` or `trace`
+pub fn parse_level(level: &str) -> Result<LevelFilter> {
+  LevelFilter::from_str(level).map_err(|_| {
+    Error::invalid_arg(format!(
+      "Unknown log level {:?}, expected one of off, error, warn, info, debug, trace",
+      level
+    ))
+  })
+}
+
+/// Sends records at `level` or more severe to `sink`, replacing the sink set
+/// by an earlier call. Fails when something else, like `env_logger`, is
+/// already the global logger.
+pub fn set_log_sink(
+  level: LevelFilter,
+  sink: impl Fn(&Record) + Send + Sync + 'static,
+) -> Result<()> {
+  INSTALL.call_once(|| INSTALLED.store(log::set_logger(&FORWARD).is_ok(), Ordering::SeqCst));
+  if !INSTALLED.load(Ordering::SeqCst) {
+    return Err(Error::failure("Another logger is already installed"));
+  }
+  *SINK.write().unwrap_or_else(|err| err.into_inner()) = Some(Box::new(sink));
+  log::set_max_level(level);
+  Ok(())
+}
+
+/// Stops sending records to the sink set by `set_log_sink`
+pub fn clear_log_sink() {
+  log::set_max_level(LevelFilter::Off);
+  *SINK.write().unwrap_or_else(|err| err.into_inner()) = None;
+}
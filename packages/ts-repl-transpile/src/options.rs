@@ -0,0 +1,294 @@
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use swc_core::common::FileName;
+use swc_core::ecma::ast::EsVersion;
+use swc_core::ecma::parser::{EsConfig, Syntax, TsConfig};
+use swc_core::ecma::transforms::module::common_js::Config as CommonJSConfig;
+use swc_core::ecma::transforms::module::util::ImportInterop;
+use swc_core::ecma::transforms::react::{Options as ReactOptions, Runtime};
+
+/// Options shared by every entry point. Anything left unset falls back to what
+/// the REPL has always done: parse decorated TypeScript, target es2016 and
+/// lower to non-strict CommonJS.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+pub struct TransformOptions {
+  /// Path of the file the source comes from. Named in diagnostics and source
+  /// maps, and decides `syntax` when that is not set.
+  pub filename: Option<String>,
+  /// "typescript", "tsx", "ecmascript" or "jsx". Defaults to what the
+  /// extension of `filename` implies, or "typescript".
+  pub syntax: Option<String>,
+  /// "preserve" (default) parses decorators and leaves them in the output,
+  /// "legacy" lowers them like TypeScript's experimentalDecorators,
+  /// "proposal" lowers them per the stage 2 proposal, "none" rejects them
+  pub decorators: Option<String>,
+  /// Emit design:type metadata when lowering legacy decorators
+  pub decorator_metadata: Option<bool>,
+  /// "es3" .. "es2022" or "esnext", defaults to "es2016"
+  pub target: Option<String>,
+  /// Emit "use strict" at the top of the output (default false)
+  pub strict_mode: Option<bool>,
+  pub module: Option<ModuleOptions>,
+  /// How JSX is lowered when `syntax` is "tsx" or "jsx"
+  pub jsx: Option<JsxOptions>,
+  /// The pass `transformSync`'s `astHook` runs after, "stripTypes" by
+  /// default. "parse" runs it on the module as parsed.
+  pub ast_hook_stage: Option<String>,
+  /// swc Wasm plugins run on the parsed source before the REPL passes, in
+  /// order. Needs the `plugin` cargo feature.
+  pub plugins: Option<Vec<PluginOptions>>,
+  /// Return a source map in `map` (default false)
+  pub source_maps: Option<bool>,
+  /// 0-based line of the document the evaluated snippet starts on, so the
+  /// source map points into the document rather than the snippet
+  pub line_offset: Option<u32>,
+  /// 0-based column the snippet's first line starts at
+  pub column_offset: Option<u32>,
+  /// How `evaluableSpans` reads its target and reports positions: "bytes"
+  /// (0-based UTF-8 offsets), "utf16" (0-based UTF-16 offsets, like
+  /// `TextDocument.offsetAt`) or "lineColumn" (UTF-16 offsets, plus each
+  /// span's `range`). Unset, they are swc's byte positions, which start at 1.
+  pub offset_encoding: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+pub struct ModuleOptions {
+  /// "commonjs" (default) lowers to `require`/`exports` for `vm.Script`.
+  /// "es" keeps `import`/`export` for `vm.SourceTextModule`, exporting every
+  /// top level binding so later snippets can import it; the other module
+  /// options don't apply to it.
+  pub r#type: Option<String>,
+  /// Define `__esModule` as non-enumerable instead of via defineProperty
+  pub strict: Option<bool>,
+  /// "swc" (default), "babel", "node" or "none"
+  pub import_interop: Option<String>,
+  /// Leave dynamic `import()` untouched
+  pub ignore_dynamic: Option<bool>,
+  /// Keep top level `this` instead of rewriting it to undefined
+  pub allow_top_level_this: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+pub struct JsxOptions {
+  /// "classic" (default) calls `pragma`, "automatic" imports from
+  /// `importSource`/jsx-runtime, which is then required like any import
+  pub runtime: Option<String>,
+  /// Classic runtime only, defaults to "React.createElement"
+  pub pragma: Option<String>,
+  /// Classic runtime only, defaults to "React.Fragment"
+  pub pragma_frag: Option<String>,
+  /// Automatic runtime only, defaults to "react"
+  pub import_source: Option<String>,
+  /// Use the development runtime (jsxDEV) with source locations
+  pub development: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+pub struct PluginOptions {
+  /// Path to the plugin's .wasm file
+  pub path: String,
+  /// Passed to the plugin as its config, like the second element of an
+  /// entry in swc's `jsc.experimental.plugins`
+  pub config: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModuleType {
+  CommonJs,
+  Es,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OffsetEncoding {
+  Bytes,
+  Utf16,
+  LineColumn,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecoratorsMode {
+  None,
+  Preserve,
+  Legacy,
+  Proposal,
+}
+
+#[derive(Debug, Clone)]
+pub struct ResolvedOptions {
+  pub file_name: FileName,
+  pub syntax: Syntax,
+  pub decorators: DecoratorsMode,
+  pub decorator_metadata: bool,
+  pub target: EsVersion,
+  pub module_type: ModuleType,
+  pub common_js: CommonJSConfig,
+  pub react: ReactOptions,
+  pub plugins: Vec<PluginOptions>,
+  pub source_maps: bool,
+  pub line_offset: u32,
+  pub column_offset: u32,
+  /// `None` for swc's positions
+  pub offset_encoding: Option<OffsetEncoding>,
+}
+
+fn invalid_option(name: &str, value: &str) -> Error {
+  Error::invalid_arg(format!("Invalid value for option `{}`: {:?}", name, value))
+}
+
+/// swc already knows how to read these from their lowercase names
+fn from_str<T: serde::de::DeserializeOwned>(name: &str, value: &str) -> Result<T> {
+  serde_json::from_value(serde_json::Value::String(value.to_lowercase()))
+    .map_err(|_| invalid_option(name, value))
+}
+
+/// The `syntax` a file is written in, going by its extension
+fn syntax_for_path(path: &Path) -> &'static str {
+  match path.extension().and_then(|ext| ext.to_str()) {
+    Some("tsx") => "tsx",
+    // like swc and babel, React code in .js files parses
+    Some("js") | Some("jsx") => "jsx",
+    Some("mjs") | Some("cjs") => "ecmascript",
+    _ => "typescript",
+  }
+}
+
+impl ResolvedOptions {
+  pub fn lowers_decorators(&self) -> bool {
+    matches!(
+      self.decorators,
+      DecoratorsMode::Legacy | DecoratorsMode::Proposal
+    )
+  }
+
+  pub fn lowers_jsx(&self) -> bool {
+    self.syntax.jsx()
+  }
+}
+
+impl JsxOptions {
+  fn resolve(&self) -> Result<ReactOptions> {
+    let runtime = match self.runtime.as_deref() {
+      None | Some("classic") => Runtime::Classic,
+      Some("automatic") => Runtime::Automatic,
+      Some(other) => return Err(invalid_option("jsx.runtime", other)),
+    };
+    Ok(ReactOptions {
+      runtime: Some(runtime),
+      pragma: self.pragma.clone(),
+      pragma_frag: self.pragma_frag.clone(),
+      import_source: self.import_source.clone(),
+      development: self.development,
+      ..Default::default()
+    })
+  }
+}
+
+impl TransformOptions {
+  pub fn resolve(&self) -> Result<ResolvedOptions> {
+    let decorators = match self.decorators.as_deref() {
+      None | Some("preserve") => DecoratorsMode::Preserve,
+      Some("legacy") => DecoratorsMode::Legacy,
+      Some("proposal") => DecoratorsMode::Proposal,
+      Some("none") => DecoratorsMode::None,
+      Some(other) => return Err(invalid_option("decorators", other)),
+    };
+    let parse_decorators = decorators != DecoratorsMode::None;
+
+    let path = self.filename.as_ref().map(PathBuf::from);
+    let syntax = self
+      .syntax
+      .as_deref()
+      .or_else(|| path.as_deref().map(syntax_for_path));
+    let syntax = match syntax {
+      None | Some("typescript") | Some("ts") => Syntax::Typescript(TsConfig {
+        decorators: parse_decorators,
+        ..Default::default()
+      }),
+      Some("tsx") => Syntax::Typescript(TsConfig {
+        tsx: true,
+        decorators: parse_decorators,
+        ..Default::default()
+      }),
+      Some("ecmascript") | Some("js") => Syntax::Es(EsConfig {
+        decorators: parse_decorators,
+        ..Default::default()
+      }),
+      Some("jsx") => Syntax::Es(EsConfig {
+        jsx: true,
+        decorators: parse_decorators,
+        ..Default::default()
+      }),
+      Some(other) => return Err(invalid_option("syntax", other)),
+    };
+
+    let target = match self.target.as_deref() {
+      None => EsVersion::Es2016,
+      Some(t) => from_str("target", t)?,
+    };
+
+    let module = self.module.clone().unwrap_or_default();
+    let module_type = match module.r#type.as_deref() {
+      None | Some("commonjs") => ModuleType::CommonJs,
+      Some("es") | Some("esm") => ModuleType::Es,
+      Some(other) => return Err(invalid_option("module.type", other)),
+    };
+    let import_interop: Option<ImportInterop> = match module.import_interop.as_deref() {
+      None => None,
+      Some(i) => Some(from_str("module.importInterop", i)?),
+    };
+
+    let react = self.jsx.clone().unwrap_or_default().resolve()?;
+
+    let offset_encoding = match self.offset_encoding.as_deref() {
+      None => None,
+      Some("bytes") => Some(OffsetEncoding::Bytes),
+      Some("utf16") => Some(OffsetEncoding::Utf16),
+      Some("lineColumn") => Some(OffsetEncoding::LineColumn),
+      Some(other) => return Err(invalid_option("offsetEncoding", other)),
+    };
+
+    let plugins = self.plugins.clone().unwrap_or_default();
+    if !plugins.is_empty() && !cfg!(feature = "plugin") {
+      return Err(Error::invalid_arg(
+        "Option `plugins` needs swc-ts-repl-transpile built with the `plugin` feature",
+      ));
+    }
+
+    Ok(ResolvedOptions {
+      file_name: path.map_or(FileName::Anon, FileName::Real),
+      syntax,
+      decorators,
+      decorator_metadata: self.decorator_metadata.unwrap_or(false),
+      target,
+      module_type,
+      common_js: CommonJSConfig {
+        strict: module.strict.unwrap_or(false),
+        strict_mode: self.strict_mode.unwrap_or(false),
+        import_interop,
+        ignore_dynamic: module.ignore_dynamic.unwrap_or(false),
+        allow_top_level_this: module.allow_top_level_this.unwrap_or(false),
+        ..Default::default()
+      },
+      react,
+      plugins,
+      source_maps: self.source_maps.unwrap_or(false),
+      line_offset: self.line_offset.unwrap_or(0),
+      column_offset: self.column_offset.unwrap_or(0),
+      offset_encoding,
+    })
+  }
+}
+
+/// Resolves options passed from JS, where the whole argument is optional
+pub fn resolve(options: Option<TransformOptions>) -> Result<ResolvedOptions> {
+  options.unwrap_or_default().resolve()
+}
@@ -0,0 +1,56 @@
+//! Turns panics into `Internal` errors, so a bug in a pass fails the call
+//! that hit it instead of taking down the process that loaded the addon.
+
+use crate::error::{Error, Result};
+use std::any::Any;
+use std::cell::RefCell;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+
+thread_local! {
+  /// Where the last panic on this thread happened, as `file:line:column`
+  static LOCATION: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+static HOOK: Once = Once::new();
+
+/// Remembers where panics happen, then runs the hook that was already set
+fn install_hook() {
+  HOOK.call_once(|| {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+      let location = info.location().map(|location| {
+        format!(
+          "{}:{}:{}",
+          location.file(),
+          location.line(),
+          location.column()
+        )
+      });
+      LOCATION.with(|last| *last.borrow_mut() = location);
+      previous(info);
+    }));
+  });
+}
+
+fn payload_message(payload: &(dyn Any + Send)) -> &str {
+  payload
+    .downcast_ref::<&str>()
+    .copied()
+    .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+    .unwrap_or("unknown panic")
+}
+
+/// Runs `f`, turning a panic into an `Internal` error that says what
+/// panicked, where, and with what message. `what` is only called on panic.
+pub fn catch<T>(what: impl FnOnce() -> String, f: impl FnOnce() -> Result<T>) -> Result<T> {
+  install_hook();
+  panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+    let location = LOCATION.with(|last| last.borrow_mut().take());
+    let message = payload_message(payload.as_ref());
+    Err(Error::internal(match location {
+      Some(location) => format!("{} panicked at {}: {}", what(), location, message),
+      None => format!("{} panicked: {}", what(), message),
+    }))
+  })
+}
@@ -0,0 +1,471 @@
+use crate::error::{Error, Result};
+use crate::options::{ModuleType, ResolvedOptions};
+use crate::panic;
+use crate::rewrite_all_to_var::{MakeExportsConfigurable, TransformAllToVar};
+use crate::session::{
+  self, ClearSyntaxContexts, InlineConstEnums, NamespaceState, TransformReport,
+};
+use crate::tla::transform_top_level_await;
+use crate::{apply_decorators, apply_react, esm, strip_types, EXPORT_STAR_HELPER};
+use log::debug;
+use std::fmt;
+use std::sync::Arc;
+use swc_core::common::comments::SingleThreadedComments;
+use swc_core::common::{Mark, SourceMap, SyntaxContext};
+use swc_core::ecma::ast::{Module, ModuleDecl, ModuleItem};
+use swc_core::ecma::transforms::base::feature::enable_available_feature_from_es_version;
+use swc_core::ecma::transforms::base::helpers::inject_helpers;
+use swc_core::ecma::transforms::base::resolver;
+use swc_core::ecma::transforms::module::common_js;
+use swc_core::ecma::utils::contains_top_level_await;
+use swc_core::ecma::visit::VisitMutWith;
+use swc_plugin_typescript_repl::TransformImportNamedToDestructuringRequireVisitor;
+
+/// What passes share while a snippet goes through the pipeline. Passes run
+/// inside swc's `GLOBALS`, `HANDLER` and `HELPERS`.
+pub struct PassContext<'a> {
+  pub cm: &'a Arc<SourceMap>,
+  pub comments: &'a SingleThreadedComments,
+  pub opts: &'a ResolvedOptions,
+  /// The namespace being evaluated in and what it has seen so far, when the
+  /// transform comes from a `ReplSession`
+  pub namespace: Option<(&'a str, &'a NamespaceState)>,
+  pub top_level_mark: Mark,
+  pub unresolved_mark: Mark,
+  /// What the snippet declares, recorded by the session afterwards
+  pub report: TransformReport,
+  /// Whether the output has to be awaited, becomes `isAsync`
+  pub is_async: bool,
+  /// Set by "exportStarHelper" for its `after_emit`
+  pub has_export_star: bool,
+}
+
+/// A step of the REPL transform. Passes are told apart by name, which is how
+/// a `Pipeline` enables, disables and positions them.
+pub trait ReplPass: Send + Sync {
+  fn name(&self) -> &str;
+
+  fn run(&self, module: Module, ctx: &mut PassContext) -> Result<Module>;
+
+  /// Runs on the emitted code, in pipeline order. Anything added here is
+  /// after what the source map covers.
+  fn after_emit(&self, _code: &mut String, _ctx: &mut PassContext) -> Result<()> {
+    Ok(())
+  }
+}
+
+/// Watches a module go through the stages of a transform
+pub type Observer<'o> = dyn FnMut(&str, &Module, &PassContext) -> Result<()> + 'o;
+
+#[derive(Clone)]
+struct Entry {
+  pass: Arc<dyn ReplPass>,
+  enabled: bool,
+}
+
+/// An ordered list of passes. `Pipeline::default()` is what `transformSync`
+/// runs; downstream crates can change it and pass it to
+/// `transform_with_pipeline` or `ReplSession::with_pipeline`.
+#[derive(Clone)]
+pub struct Pipeline {
+  entries: Vec<Entry>,
+}
+
+impl fmt::Debug for Pipeline {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_list()
+      .entries(
+        self
+          .entries
+          .iter()
+          .map(|entry| (entry.pass.name(), entry.enabled)),
+      )
+      .finish()
+  }
+}
+
+fn no_such_pass(name: &str) -> Error {
+  Error::invalid_arg(format!("No pass named {:?} in the pipeline", name))
+}
+
+impl Pipeline {
+  /// A pipeline without any passes
+  pub fn empty() -> Self {
+    Pipeline { entries: vec![] }
+  }
+
+  fn position(&self, name: &str) -> Result<usize> {
+    self
+      .entries
+      .iter()
+      .position(|entry| entry.pass.name() == name)
+      .ok_or_else(|| no_such_pass(name))
+  }
+
+  /// Names of the passes in the order they run, disabled ones included
+  pub fn names(&self) -> Vec<&str> {
+    self.entries.iter().map(|entry| entry.pass.name()).collect()
+  }
+
+  pub fn is_enabled(&self, name: &str) -> Result<bool> {
+    Ok(self.entries[self.position(name)?].enabled)
+  }
+
+  pub fn push(&mut self, pass: impl ReplPass + 'static) -> &mut Self {
+    self.entries.push(Entry {
+      pass: Arc::new(pass),
+      enabled: true,
+    });
+    self
+  }
+
+  pub fn insert_before(&mut self, name: &str, pass: impl ReplPass + 'static) -> Result<&mut Self> {
+    let index = self.position(name)?;
+    self.entries.insert(
+      index,
+      Entry {
+        pass: Arc::new(pass),
+        enabled: true,
+      },
+    );
+    Ok(self)
+  }
+
+  pub fn insert_after(&mut self, name: &str, pass: impl ReplPass + 'static) -> Result<&mut Self> {
+    let index = self.position(name)?;
+    self.entries.insert(
+      index + 1,
+      Entry {
+        pass: Arc::new(pass),
+        enabled: true,
+      },
+    );
+    Ok(self)
+  }
+
+  /// Swaps the pass called `name` for `pass`, keeping its position
+  pub fn replace(&mut self, name: &str, pass: impl ReplPass + 'static) -> Result<&mut Self> {
+    let index = self.position(name)?;
+    self.entries[index].pass = Arc::new(pass);
+    Ok(self)
+  }
+
+  pub fn remove(&mut self, name: &str) -> Result<&mut Self> {
+    let index = self.position(name)?;
+    self.entries.remove(index);
+    Ok(self)
+  }
+
+  /// Moves the pass called `name` so it runs right before `before`
+  pub fn move_before(&mut self, name: &str, before: &str) -> Result<&mut Self> {
+    let from = self.position(name)?;
+    let entry = self.entries.remove(from);
+    match self.position(before) {
+      Ok(index) => self.entries.insert(index, entry),
+      Err(err) => {
+        self.entries.insert(from, entry);
+        return Err(err);
+      }
+    }
+    Ok(self)
+  }
+
+  pub fn set_enabled(&mut self, name: &str, enabled: bool) -> Result<&mut Self> {
+    let index = self.position(name)?;
+    self.entries[index].enabled = enabled;
+    Ok(self)
+  }
+
+  pub fn run(&self, module: Module, ctx: &mut PassContext) -> Result<Module> {
+    self.run_observed(module, ctx, &mut |_, _, _| Ok(()))
+  }
+
+  /// `run`, calling `observer` with each pass's name and output as it goes
+  pub fn run_observed(
+    &self,
+    module: Module,
+    ctx: &mut PassContext,
+    observer: &mut Observer,
+  ) -> Result<Module> {
+    let mut module = module;
+    for entry in self.entries.iter().filter(|entry| entry.enabled) {
+      debug!("running pass {}", entry.pass.name());
+      module = panic::catch(
+        || format!("The {} pass", entry.pass.name()),
+        || entry.pass.run(module, ctx),
+      )?;
+      observer(entry.pass.name(), &module, ctx)?;
+    }
+    Ok(module)
+  }
+
+  pub fn after_emit(&self, code: &mut String, ctx: &mut PassContext) -> Result<()> {
+    for entry in self.entries.iter().filter(|entry| entry.enabled) {
+      panic::catch(
+        || format!("The {} pass", entry.pass.name()),
+        || entry.pass.after_emit(code, ctx),
+      )?;
+    }
+    Ok(())
+  }
+}
+
+/// The REPL transform as it has always run. Passes that only apply to one
+/// module type do nothing for the other.
+impl Default for Pipeline {
+  fn default() -> Self {
+    let mut pipeline = Pipeline::empty();
+    pipeline
+      .push(InlineSessionConstEnums)
+      .push(ExportStarHelper)
+      .push(ImportRewrite)
+      .push(Decorators)
+      .push(StripTypes)
+      .push(React)
+      .push(EsmExports)
+      .push(CommonJs)
+      .push(InjectHelpers)
+      .push(AllToVar)
+      .push(ExportsConfigurable)
+      .push(TopLevelAwait);
+    pipeline
+  }
+}
+
+fn is_es(ctx: &PassContext) -> bool {
+  ctx.opts.module_type == ModuleType::Es
+}
+
+/// "inlineConstEnums": replaces members of const enums earlier evaluations
+/// in the namespace declared with their values
+pub struct InlineSessionConstEnums;
+
+impl ReplPass for InlineSessionConstEnums {
+  fn name(&self) -> &str {
+    "inlineConstEnums"
+  }
+
+  fn run(&self, mut module: Module, ctx: &mut PassContext) -> Result<Module> {
+    let enums = match ctx.namespace {
+      Some((_, ns)) if !ns.const_enums.is_empty() => &ns.const_enums,
+      _ => return Ok(module),
+    };
+    // scopes decide which `E`s are the session's enum
+    module.visit_mut_with(&mut resolver(ctx.unresolved_mark, ctx.top_level_mark, true));
+    module.visit_mut_with(&mut InlineConstEnums {
+      enums,
+      unresolved: SyntaxContext::empty().apply_mark(ctx.unresolved_mark),
+    });
+    module.visit_mut_with(&mut ClearSyntaxContexts);
+    Ok(module)
+  }
+}
+
+/// "exportStarHelper": `common_js` calls `_exportStar` for `export *` without
+/// defining it, so the helper is appended once per namespace
+pub struct ExportStarHelper;
+
+impl ReplPass for ExportStarHelper {
+  fn name(&self) -> &str {
+    "exportStarHelper"
+  }
+
+  fn run(&self, module: Module, ctx: &mut PassContext) -> Result<Module> {
+    ctx.has_export_star = !is_es(ctx)
+      && module
+        .body
+        .iter()
+        .any(|item| matches!(item, ModuleItem::ModuleDecl(ModuleDecl::ExportAll(_))));
+    Ok(module)
+  }
+
+  fn after_emit(&self, code: &mut String, ctx: &mut PassContext) -> Result<()> {
+    let installed = ctx
+      .namespace
+      .is_some_and(|(_, ns)| ns.helpers.contains(session::EXPORT_STAR));
+    if ctx.has_export_star && !installed {
+      code.push('\n');
+      code.push_str(EXPORT_STAR_HELPER);
+      ctx.report.helpers.push(session::EXPORT_STAR.into());
+    }
+    Ok(())
+  }
+}
+
+/// "importRewrite": turns imports into `require` destructuring, CommonJS only
+pub struct ImportRewrite;
+
+impl ReplPass for ImportRewrite {
+  fn name(&self) -> &str {
+    "importRewrite"
+  }
+
+  fn run(&self, mut module: Module, ctx: &mut PassContext) -> Result<Module> {
+    if !is_es(ctx) {
+      module.visit_mut_with(&mut TransformImportNamedToDestructuringRequireVisitor);
+    }
+    Ok(module)
+  }
+}
+
+/// "decorators"
+pub struct Decorators;
+
+impl ReplPass for Decorators {
+  fn name(&self) -> &str {
+    "decorators"
+  }
+
+  fn run(&self, module: Module, ctx: &mut PassContext) -> Result<Module> {
+    Ok(apply_decorators(module, ctx.opts))
+  }
+}
+
+/// "stripTypes"
+pub struct StripTypes;
+
+impl ReplPass for StripTypes {
+  fn name(&self) -> &str {
+    "stripTypes"
+  }
+
+  fn run(&self, module: Module, ctx: &mut PassContext) -> Result<Module> {
+    Ok(strip_types(
+      module,
+      ctx.cm,
+      ctx.comments,
+      ctx.opts,
+      ctx.top_level_mark,
+    ))
+  }
+}
+
+/// "react"
+pub struct React;
+
+impl ReplPass for React {
+  fn name(&self) -> &str {
+    "react"
+  }
+
+  fn run(&self, module: Module, ctx: &mut PassContext) -> Result<Module> {
+    Ok(apply_react(
+      module,
+      ctx.cm,
+      ctx.comments,
+      ctx.opts,
+      ctx.top_level_mark,
+    ))
+  }
+}
+
+/// "esmExports": ES modules only. Modules can await at the top level and
+/// redeclare what an earlier module declared, so the var and async iife
+/// rewrites aren't needed; bindings are passed on through imports and exports
+/// instead.
+pub struct EsmExports;
+
+impl ReplPass for EsmExports {
+  fn name(&self) -> &str {
+    "esmExports"
+  }
+
+  fn run(&self, mut module: Module, ctx: &mut PassContext) -> Result<Module> {
+    if is_es(ctx) {
+      if let Some((name, ns)) = ctx.namespace {
+        esm::import_namespace_bindings(&mut module, name, &ns.bindings);
+      }
+      esm::export_top_level_bindings(&mut module);
+    }
+    Ok(module)
+  }
+}
+
+/// "commonJs"
+pub struct CommonJs;
+
+impl ReplPass for CommonJs {
+  fn name(&self) -> &str {
+    "commonJs"
+  }
+
+  fn run(&self, mut module: Module, ctx: &mut PassContext) -> Result<Module> {
+    if !is_es(ctx) {
+      module.visit_mut_with(&mut common_js(
+        ctx.unresolved_mark,
+        ctx.opts.common_js.clone(),
+        enable_available_feature_from_es_version(ctx.opts.target),
+        Some(ctx.comments),
+      ));
+    }
+    Ok(module)
+  }
+}
+
+/// "injectHelpers": inlines the swc helpers lowered decorators and JSX use
+pub struct InjectHelpers;
+
+impl ReplPass for InjectHelpers {
+  fn name(&self) -> &str {
+    "injectHelpers"
+  }
+
+  fn run(&self, mut module: Module, ctx: &mut PassContext) -> Result<Module> {
+    if ctx.opts.lowers_decorators() || ctx.opts.lowers_jsx() {
+      module.visit_mut_with(&mut inject_helpers());
+    }
+    Ok(module)
+  }
+}
+
+/// "allToVar", CommonJS only
+pub struct AllToVar;
+
+impl ReplPass for AllToVar {
+  fn name(&self) -> &str {
+    "allToVar"
+  }
+
+  fn run(&self, mut module: Module, ctx: &mut PassContext) -> Result<Module> {
+    if !is_es(ctx) {
+      module.visit_mut_with(&mut TransformAllToVar);
+    }
+    Ok(module)
+  }
+}
+
+/// "exportsConfigurable", CommonJS only
+pub struct ExportsConfigurable;
+
+impl ReplPass for ExportsConfigurable {
+  fn name(&self) -> &str {
+    "exportsConfigurable"
+  }
+
+  fn run(&self, mut module: Module, ctx: &mut PassContext) -> Result<Module> {
+    if !is_es(ctx) {
+      module.visit_mut_with(&mut MakeExportsConfigurable);
+    }
+    Ok(module)
+  }
+}
+
+/// "topLevelAwait": decides `is_async`. For CommonJS a top level await also
+/// moves the code into an async iife.
+pub struct TopLevelAwait;
+
+impl ReplPass for TopLevelAwait {
+  fn name(&self) -> &str {
+    "topLevelAwait"
+  }
+
+  fn run(&self, module: Module, ctx: &mut PassContext) -> Result<Module> {
+    if is_es(ctx) {
+      ctx.is_async = contains_top_level_await(&module);
+      return Ok(module);
+    }
+    let tla = transform_top_level_await(&module);
+    ctx.is_async = tla.has_top_level_await;
+    Ok(tla.module.unwrap_or(module))
+  }
+}
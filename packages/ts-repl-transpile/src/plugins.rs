@@ -0,0 +1,84 @@
+use crate::error::Result;
+use crate::options::ResolvedOptions;
+use std::sync::Arc;
+use swc_core::common::comments::SingleThreadedComments;
+use swc_core::common::{Mark, SourceMap};
+use swc_core::ecma::ast::Module;
+
+// Runs the user's swc Wasm plugins, the way swc runs `jsc.experimental.plugins`:
+// the program is serialized, handed to each plugin in turn and read back.
+
+#[cfg(feature = "plugin")]
+pub fn apply(
+  module: Module,
+  cm: &Arc<SourceMap>,
+  comments: &SingleThreadedComments,
+  opts: &ResolvedOptions,
+  unresolved_mark: Mark,
+) -> Result<Module> {
+  use crate::error::Error;
+  use std::path::Path;
+  use swc_core::common::plugin::metadata::TransformPluginMetadataContext;
+  use swc_core::common::plugin::serialized::{PluginSerializedBytes, VersionedSerializable};
+  use swc_core::ecma::ast::Program;
+  use swc_core::plugin::proxies::{HostCommentsStorage, COMMENTS};
+  use swc_core::plugin_runner::cache::PLUGIN_MODULE_CACHE;
+  use swc_core::plugin_runner::create_plugin_transform_executor;
+
+  if opts.plugins.is_empty() {
+    return Ok(module);
+  }
+
+  let failed =
+    |path: &str, err: anyhow::Error| Error::failure(format!("Plugin {} failed: {:?}", path, err));
+  let metadata = Arc::new(TransformPluginMetadataContext::new(
+    Some(opts.file_name.to_string()),
+    "development".into(),
+    None,
+  ));
+  let mut serialized =
+    PluginSerializedBytes::try_serialize(&VersionedSerializable::new(Program::Module(module)))
+      .map_err(|err| Error::failure(format!("Could not serialize the module: {:?}", err)))?;
+
+  let storage = HostCommentsStorage {
+    inner: Some(comments.clone()),
+  };
+  COMMENTS.set(&storage, || -> Result<()> {
+    for plugin in &opts.plugins {
+      let mut executor = create_plugin_transform_executor(
+        Path::new(&plugin.path),
+        &PLUGIN_MODULE_CACHE,
+        cm,
+        &metadata,
+        plugin.config.clone(),
+      )
+      .map_err(|err| failed(&plugin.path, err))?;
+      serialized = executor
+        .transform(&serialized, unresolved_mark, true)
+        .map_err(|err| failed(&plugin.path, err))?;
+    }
+    Ok(())
+  })?;
+
+  let program: Program = serialized
+    .deserialize()
+    .map_err(|err| Error::failure(format!("Could not read a plugin's output: {:?}", err)))?
+    .into_inner();
+  match program {
+    Program::Module(module) => Ok(module),
+    Program::Script(_) => Err(Error::failure("A plugin turned the module into a script")),
+  }
+}
+
+/// Without the `plugin` feature `plugins` is rejected when options are
+/// resolved, so there is never anything to run
+#[cfg(not(feature = "plugin"))]
+pub fn apply(
+  module: Module,
+  _cm: &Arc<SourceMap>,
+  _comments: &SingleThreadedComments,
+  _opts: &ResolvedOptions,
+  _unresolved_mark: Mark,
+) -> Result<Module> {
+  Ok(module)
+}
@@ -0,0 +1,539 @@
+use crate::diagnostics::Diagnostic;
+use crate::error::{Error, Result};
+use crate::options::{self, TransformOptions};
+use crate::pipeline::Pipeline;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+use swc_core::common::SyntaxContext;
+use swc_core::ecma::ast::*;
+use swc_core::ecma::utils::find_pat_ids;
+use swc_core::ecma::visit::*;
+
+/// Name recorded in [NamespaceState::helpers] once `_exportStar` is defined
+pub const EXPORT_STAR: &str = "exportStar";
+
+/// Bumped whenever a saved session can no longer be read as is
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// Source the session transformed for a namespace, in the order it did so
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+pub struct Evaluation {
+  pub source: String,
+  /// Top level bindings the source declared
+  pub declared: Vec<String>,
+}
+
+/// What the session knows about one namespace from the evaluations it has
+/// transformed so far
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NamespaceState {
+  /// Top level bindings in the order they were first declared
+  pub bindings: Vec<String>,
+  /// const enum name to member values
+  pub const_enums: BTreeMap<String, BTreeMap<String, Value>>,
+  /// Helpers already defined in the namespace's context
+  pub helpers: BTreeSet<String>,
+  /// Used when a transform is not given options of its own
+  pub options: Option<TransformOptions>,
+  /// Everything transformed without errors, enough to replay the namespace
+  /// into a fresh context
+  pub history: Vec<Evaluation>,
+}
+
+impl NamespaceState {
+  /// The state of a namespace whose context was lost, like after a restart.
+  /// Its bindings, helpers and const enums were values in the old context,
+  /// so only its options and history, to replay, are kept.
+  fn restored(self) -> Self {
+    NamespaceState {
+      options: self.options,
+      history: self.history,
+      ..Default::default()
+    }
+  }
+
+  fn declare(&mut self, name: &str) -> bool {
+    if self.bindings.iter().any(|b| b == name) {
+      return true;
+    }
+    self.bindings.push(name.to_string());
+    false
+  }
+
+  /// Records what a successful transform declared. Returns the names that
+  /// were already bound.
+  pub fn record(&mut self, report: TransformReport) -> Vec<String> {
+    let redeclared = report
+      .declared
+      .iter()
+      .filter(|name| self.declare(name))
+      .cloned()
+      .collect();
+    for (name, members) in report.const_enums {
+      self.const_enums.insert(name, members);
+    }
+    self.helpers.extend(report.helpers);
+    redeclared
+  }
+}
+
+/// What a transform found out about the code it was given, for the session
+/// to record once the code is known to be good
+#[derive(Debug, Default)]
+pub struct TransformReport {
+  pub declared: Vec<String>,
+  pub const_enums: Vec<(String, BTreeMap<String, Value>)>,
+  pub helpers: Vec<String>,
+}
+
+pub fn decl_names(decl: &Decl, names: &mut Vec<String>) {
+  match decl {
+    Decl::Class(c) => names.push(c.ident.sym.to_string()),
+    Decl::Fn(f) => names.push(f.ident.sym.to_string()),
+    Decl::Var(v) => {
+      for d in &v.decls {
+        let ids: Vec<Ident> = find_pat_ids(&d.name);
+        names.extend(ids.into_iter().map(|id| id.sym.to_string()));
+      }
+    }
+    Decl::TsEnum(e) => names.push(e.id.sym.to_string()),
+    Decl::TsModule(m) => {
+      if let TsModuleName::Ident(id) = &m.id {
+        names.push(id.sym.to_string())
+      }
+    }
+    // types only exist for the type checker
+    Decl::TsInterface(_) | Decl::TsTypeAlias(_) => {}
+  }
+}
+
+/// Names bound at the top level of `module`, in source order
+pub fn top_level_bindings(module: &Module) -> Vec<String> {
+  let mut names = vec![];
+  for item in &module.body {
+    match item {
+      ModuleItem::ModuleDecl(ModuleDecl::Import(import)) if !import.type_only => {
+        for specifier in &import.specifiers {
+          let local = match specifier {
+            ImportSpecifier::Named(s) if !s.is_type_only => &s.local,
+            ImportSpecifier::Named(_) => continue,
+            ImportSpecifier::Default(s) => &s.local,
+            ImportSpecifier::Namespace(s) => &s.local,
+          };
+          names.push(local.sym.to_string());
+        }
+      }
+      ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(export)) => {
+        decl_names(&export.decl, &mut names)
+      }
+      ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultDecl(export)) => match &export.decl {
+        DefaultDecl::Class(ClassExpr {
+          ident: Some(id), ..
+        })
+        | DefaultDecl::Fn(FnExpr {
+          ident: Some(id), ..
+        }) => names.push(id.sym.to_string()),
+        _ => {}
+      },
+      ModuleItem::Stmt(Stmt::Decl(decl)) => decl_names(decl, &mut names),
+      _ => {}
+    }
+  }
+  names
+}
+
+fn literal_value(expr: &Expr) -> Option<Value> {
+  match expr {
+    Expr::Lit(Lit::Num(n)) => serde_json::Number::from_f64(n.value).map(Value::Number),
+    Expr::Lit(Lit::Str(s)) => Some(Value::String(s.value.to_string())),
+    Expr::Unary(UnaryExpr {
+      op: UnaryOp::Minus,
+      arg,
+      ..
+    }) => match &**arg {
+      Expr::Lit(Lit::Num(n)) => serde_json::Number::from_f64(-n.value).map(Value::Number),
+      _ => None,
+    },
+    Expr::Paren(p) => literal_value(&p.expr),
+    _ => None,
+  }
+}
+
+fn member_name(id: &TsEnumMemberId) -> String {
+  match id {
+    TsEnumMemberId::Ident(i) => i.sym.to_string(),
+    TsEnumMemberId::Str(s) => s.value.to_string(),
+  }
+}
+
+/// Values of the members of a const enum. Members initialized with anything
+/// other than a literal are left out, as is everything after them that relies
+/// on auto-incrementing.
+fn const_enum_values(e: &TsEnumDecl) -> BTreeMap<String, Value> {
+  let mut values = BTreeMap::new();
+  let mut next = Some(0.0);
+  for member in &e.members {
+    let value = match &member.init {
+      Some(init) => literal_value(init),
+      None => next.and_then(|n| serde_json::Number::from_f64(n).map(Value::Number)),
+    };
+    next = match &value {
+      Some(Value::Number(n)) => n.as_f64().map(|n| n + 1.0),
+      _ => None,
+    };
+    if let Some(value) = value {
+      values.insert(member_name(&member.id), value);
+    }
+  }
+  values
+}
+
+/// const enums declared at the top level of `module`
+pub fn top_level_const_enums(module: &Module) -> Vec<(String, BTreeMap<String, Value>)> {
+  module
+    .body
+    .iter()
+    .filter_map(|item| match item {
+      ModuleItem::Stmt(Stmt::Decl(Decl::TsEnum(e))) => Some(e),
+      ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl {
+        decl: Decl::TsEnum(e),
+        ..
+      })) => Some(e),
+      _ => None,
+    })
+    .filter(|e| e.is_const)
+    .map(|e| (e.id.sym.to_string(), const_enum_values(e)))
+    .collect()
+}
+
+/// Replaces `E.A` with the value of a const enum member declared by an earlier
+/// evaluation. The typescript strip only inlines enums declared in the same
+/// snippet, so without this the access would read a binding the strip never
+/// emitted. Runs on a module that went through `resolver`, and only replaces
+/// `E`s that aren't bound anywhere in the snippet: enums the snippet declares
+/// again itself are left to the strip, and parameters or locals called `E`
+/// are left alone.
+pub struct InlineConstEnums<'a> {
+  pub enums: &'a BTreeMap<String, BTreeMap<String, Value>>,
+  /// What `resolver` gives identifiers bound outside the snippet
+  pub unresolved: SyntaxContext,
+}
+
+impl InlineConstEnums<'_> {
+  fn lookup(&self, member: &MemberExpr) -> Option<&Value> {
+    let obj = match &*member.obj {
+      Expr::Ident(obj) => obj,
+      _ => return None,
+    };
+    if obj.span.ctxt != self.unresolved {
+      return None;
+    }
+    let prop = match &member.prop {
+      MemberProp::Ident(prop) => prop.sym.to_string(),
+      MemberProp::Computed(ComputedPropName { expr, .. }) => match &**expr {
+        Expr::Lit(Lit::Str(s)) => s.value.to_string(),
+        _ => return None,
+      },
+      MemberProp::PrivateName(_) => return None,
+    };
+    self.enums.get(&*obj.sym)?.get(&prop)
+  }
+}
+
+impl VisitMut for InlineConstEnums<'_> {
+  fn visit_mut_expr(&mut self, expr: &mut Expr) {
+    expr.visit_mut_children_with(self);
+
+    let (span, value) = match expr {
+      Expr::Member(member) => match self.lookup(member) {
+        Some(value) => (member.span, value.clone()),
+        None => return,
+      },
+      _ => return,
+    };
+    let lit = match value {
+      Value::Number(n) => match n.as_f64() {
+        Some(value) => Lit::Num(Number {
+          span,
+          value,
+          raw: None,
+        }),
+        None => return,
+      },
+      Value::String(s) => Lit::Str(Str {
+        span,
+        value: s.as_str().into(),
+        raw: None,
+      }),
+      _ => return,
+    };
+    *expr = Expr::Lit(lit);
+  }
+}
+
+/// Takes the marks `resolver` left on identifiers off again, for the passes
+/// that run on the module as parsed
+pub(crate) struct ClearSyntaxContexts;
+
+impl VisitMut for ClearSyntaxContexts {
+  fn visit_mut_ident(&mut self, ident: &mut Ident) {
+    ident.span.ctxt = SyntaxContext::empty();
+  }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+pub struct SessionTransformOutput {
+  pub code: String,
+  pub is_async: bool,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub map: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub diagnostics: Option<Vec<Diagnostic>>,
+  /// Top level bindings the code declares
+  pub declared: Vec<String>,
+  /// The subset of `declared` an earlier evaluation in the namespace had
+  /// already declared
+  pub redeclared: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+pub struct ConstEnumMember {
+  pub name: String,
+  /// A number or a string
+  pub value: Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+pub struct ConstEnum {
+  pub name: String,
+  pub members: Vec<ConstEnumMember>,
+}
+
+/// What gets written to disk. Namespaces are sorted so saving the same
+/// session twice gives the same file.
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+  version: u32,
+  namespaces: BTreeMap<String, NamespaceState>,
+}
+
+fn invalid_session(err: impl std::fmt::Display) -> Error {
+  Error::invalid_arg(format!("Invalid session: {}", err))
+}
+
+fn io_error(path: &str, err: std::io::Error) -> Error {
+  Error::failure(format!("{}: {}", path, err))
+}
+
+/// Remembers what each namespace of a REPL has evaluated so later transforms
+/// in the same namespace can build on it
+#[derive(Default)]
+pub struct ReplSession {
+  namespaces: HashMap<String, NamespaceState>,
+  pipeline: Pipeline,
+}
+
+impl ReplSession {
+  pub fn new() -> Self {
+    Default::default()
+  }
+
+  /// A session whose transforms run `pipeline` instead of the default one
+  pub fn with_pipeline(pipeline: Pipeline) -> Self {
+    ReplSession {
+      pipeline,
+      ..Default::default()
+    }
+  }
+
+  /// Runs `pipeline` for the transforms from now on
+  pub fn set_pipeline(&mut self, pipeline: Pipeline) {
+    self.pipeline = pipeline;
+  }
+
+  /// Sets the options used by transforms in `namespace` that don't pass
+  /// their own
+  pub fn set_options(&mut self, namespace: String, options: TransformOptions) -> Result<()> {
+    options.resolve()?;
+    self.namespaces.entry(namespace).or_default().options = Some(options);
+    Ok(())
+  }
+
+  /// Same as `transformSync`, for code that will be evaluated in
+  /// `namespace`. What the code declares is only recorded when it transforms
+  /// without errors.
+  pub fn transform(
+    &mut self,
+    namespace: String,
+    source: String,
+    options: Option<TransformOptions>,
+  ) -> Result<SessionTransformOutput> {
+    let state = self.namespaces.entry(namespace.clone()).or_default();
+    let opts = options::resolve(options.or_else(|| state.options.clone()))?;
+    let (output, report) = crate::transform_repl(
+      source.clone(),
+      &opts,
+      Some((&namespace, &*state)),
+      &self.pipeline,
+      &mut |_, _, _| Ok(()),
+    )?;
+
+    let failed = output
+      .diagnostics
+      .as_ref()
+      .is_some_and(|d| d.iter().any(Diagnostic::is_error));
+    let declared = report.declared.clone();
+    let redeclared = if failed {
+      vec![]
+    } else {
+      state.history.push(Evaluation {
+        source,
+        declared: declared.clone(),
+      });
+      state.record(report)
+    };
+
+    Ok(SessionTransformOutput {
+      code: output.code,
+      is_async: output.is_async,
+      map: output.map,
+      diagnostics: output.diagnostics,
+      declared,
+      redeclared,
+    })
+  }
+
+  /// Top level bindings declared in `namespace` so far
+  pub fn bindings(&self, namespace: String) -> Vec<String> {
+    self
+      .namespaces
+      .get(&namespace)
+      .map(|ns| ns.bindings.clone())
+      .unwrap_or_default()
+  }
+
+  pub fn const_enums(&self, namespace: String) -> Vec<ConstEnum> {
+    let enums = match self.namespaces.get(&namespace) {
+      Some(ns) => &ns.const_enums,
+      None => return vec![],
+    };
+    enums
+      .iter()
+      .map(|(name, members)| ConstEnum {
+        name: name.clone(),
+        members: members
+          .iter()
+          .map(|(name, value)| ConstEnumMember {
+            name: name.clone(),
+            value: value.clone(),
+          })
+          .collect(),
+      })
+      .collect()
+  }
+
+  /// What was evaluated in `namespace`, oldest first
+  pub fn history(&self, namespace: String) -> Vec<Evaluation> {
+    self
+      .namespaces
+      .get(&namespace)
+      .map(|ns| ns.history.clone())
+      .unwrap_or_default()
+  }
+
+  /// Helpers already defined in `namespace`
+  pub fn helpers(&self, namespace: String) -> Vec<String> {
+    self
+      .namespaces
+      .get(&namespace)
+      .map(|ns| ns.helpers.iter().cloned().collect())
+      .unwrap_or_default()
+  }
+
+  pub fn namespaces(&self) -> Vec<String> {
+    let mut names: Vec<String> = self.namespaces.keys().cloned().collect();
+    names.sort();
+    names
+  }
+
+  /// Forgets a binding, for when it is removed from the namespace's context
+  pub fn unmap(&mut self, namespace: String, binding: String) {
+    if let Some(ns) = self.namespaces.get_mut(&namespace) {
+      ns.bindings.retain(|b| *b != binding);
+      ns.const_enums.remove(&binding);
+    }
+  }
+
+  /// Forgets everything about `namespace`, or every namespace when none is
+  /// given
+  pub fn reset(&mut self, namespace: Option<String>) {
+    match namespace {
+      Some(namespace) => {
+        self.namespaces.remove(&namespace);
+      }
+      None => self.namespaces.clear(),
+    }
+  }
+
+  /// Serializes every namespace's state, for `fromJson`. The pipeline isn't
+  /// part of it, since its passes can be any Rust code.
+  pub fn to_json(&self) -> Result<String> {
+    let snapshot = Snapshot {
+      version: SNAPSHOT_VERSION,
+      namespaces: self
+        .namespaces
+        .iter()
+        .map(|(name, ns)| (name.clone(), ns.clone()))
+        .collect(),
+    };
+    serde_json::to_string(&snapshot).map_err(|err| Error::failure(err.to_string()))
+  }
+
+  /// Restores a session serialized by `to_json`, for namespaces evaluated in
+  /// fresh contexts: each keeps its options and history, but not what was
+  /// defined in its old context. It runs the default pipeline, whatever the
+  /// saved session ran; `set_pipeline` puts a custom one back.
+  pub fn from_json(json: String) -> Result<Self> {
+    let snapshot: Snapshot = serde_json::from_str(&json).map_err(invalid_session)?;
+    if snapshot.version != SNAPSHOT_VERSION {
+      return Err(invalid_session(format!(
+        "unsupported version {}",
+        snapshot.version
+      )));
+    }
+    Ok(ReplSession {
+      namespaces: snapshot
+        .namespaces
+        .into_iter()
+        .map(|(name, ns)| (name, ns.restored()))
+        .collect(),
+      ..Default::default()
+    })
+  }
+
+  /// Writes the session to `path`, creating its directory if needed
+  pub fn save(&self, path: String) -> Result<()> {
+    let json = self.to_json()?;
+    if let Some(dir) = Path::new(&path).parent() {
+      fs::create_dir_all(dir).map_err(|err| io_error(&path, err))?;
+    }
+    fs::write(&path, json).map_err(|err| io_error(&path, err))
+  }
+
+  /// Reads a session written by `save`, with the default pipeline like
+  /// `from_json`
+  pub fn load(path: String) -> Result<Self> {
+    let json = fs::read_to_string(&path).map_err(|err| io_error(&path, err))?;
+    Self::from_json(json)
+  }
+}
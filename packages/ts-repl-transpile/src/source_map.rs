@@ -0,0 +1,38 @@
+use sourcemap::{SourceMap, SourceMapBuilder};
+
+/// Moves every original position in `map` by where the snippet sits in its
+/// document. Only the snippet's first line starts part way through a line, so
+/// the column offset applies to that line alone.
+pub fn offset_source_map(map: &SourceMap, line_offset: u32, column_offset: u32) -> SourceMap {
+  if line_offset == 0 && column_offset == 0 {
+    return map.clone();
+  }
+  let mut builder = SourceMapBuilder::new(map.get_file());
+  for token in map.tokens() {
+    let src_line = token.get_src_line();
+    let src_col = if src_line == 0 {
+      token.get_src_col() + column_offset
+    } else {
+      token.get_src_col()
+    };
+    builder.add(
+      token.get_dst_line(),
+      token.get_dst_col(),
+      src_line + line_offset,
+      src_col,
+      token.get_source(),
+      token.get_name(),
+    );
+  }
+  for (idx, source) in map.sources().enumerate() {
+    let id = builder.add_source(source);
+    builder.set_source_contents(id, map.get_source_contents(idx as u32));
+  }
+  builder.into_sourcemap()
+}
+
+pub fn to_json(map: &SourceMap) -> anyhow::Result<String> {
+  let mut buf = vec![];
+  map.to_writer(&mut buf)?;
+  Ok(String::from_utf8(buf)?)
+}
@@ -0,0 +1,124 @@
+use crate::error::{Error, Result};
+use serde::Serialize;
+use sourcemap::SourceMap;
+
+/// One `at ...` line of a V8 stack. Lines and columns are 1-based like V8's.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+pub struct StackFrame {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub function_name: Option<String>,
+  pub file: String,
+  pub line: u32,
+  pub column: u32,
+  /// Whether the position was translated through the source map
+  pub mapped: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "napi", napi_derive::napi(object))]
+pub struct MappedStackTrace {
+  /// The stack with every frame from the evaluated code rewritten
+  pub stack: String,
+  pub frames: Vec<StackFrame>,
+}
+
+struct ParsedFrame<'a> {
+  indent: &'a str,
+  function_name: Option<&'a str>,
+  file: &'a str,
+  line: u32,
+  column: u32,
+}
+
+/// Parses `    at fn (file:1:2)` and `    at file:1:2`
+fn parse_frame(line: &str) -> Option<ParsedFrame<'_>> {
+  let trimmed = line.trim_start();
+  let indent = &line[..line.len() - trimmed.len()];
+  let rest = trimmed.strip_prefix("at ")?;
+
+  let (function_name, location) = match rest.strip_suffix(')') {
+    Some(rest) => {
+      let open = rest.rfind(" (")?;
+      (Some(&rest[..open]), &rest[open + 2..])
+    }
+    None => (None, rest),
+  };
+
+  let mut parts = location.rsplitn(3, ':');
+  let column = parts.next()?.parse().ok()?;
+  let line = parts.next()?.parse().ok()?;
+  let file = parts.next()?;
+
+  Some(ParsedFrame {
+    indent,
+    function_name,
+    file,
+    line,
+    column,
+  })
+}
+
+/// Finds the original position of a 1-based generated position
+fn original_position(map: &SourceMap, line: u32, column: u32) -> Option<(u32, u32)> {
+  let (dst_line, dst_col) = (line.checked_sub(1)?, column.checked_sub(1)?);
+  let token = map.lookup_token(dst_line, dst_col)?;
+  if token.get_dst_line() != dst_line {
+    return None;
+  }
+  // tokens mark where a mapped range starts, keep our distance from it
+  let col = token.get_src_col() + (dst_col - token.get_dst_col());
+  Some((token.get_src_line() + 1, col + 1))
+}
+
+pub fn map_stack(stack: &str, map: &SourceMap, filename: &str) -> MappedStackTrace {
+  let mut frames = vec![];
+  let lines: Vec<String> = stack
+    .lines()
+    .map(|line| {
+      let frame = match parse_frame(line) {
+        Some(frame) => frame,
+        None => return line.to_string(),
+      };
+
+      let position = if frame.file == filename {
+        original_position(map, frame.line, frame.column)
+      } else {
+        None
+      };
+      let (line_no, column) = position.unwrap_or((frame.line, frame.column));
+
+      frames.push(StackFrame {
+        function_name: frame.function_name.map(String::from),
+        file: frame.file.to_string(),
+        line: line_no,
+        column,
+        mapped: position.is_some(),
+      });
+
+      match frame.function_name {
+        Some(name) => format!(
+          "{}at {} ({}:{}:{})",
+          frame.indent, name, frame.file, line_no, column
+        ),
+        None => format!("{}at {}:{}:{}", frame.indent, frame.file, line_no, column),
+      }
+    })
+    .collect();
+
+  MappedStackTrace {
+    stack: lines.join("\n"),
+    frames,
+  }
+}
+
+/// Rewrites a stack produced by running a transform's output with
+/// `vm.runInContext(code, ctx, { filename })` so frames from that evaluation
+/// point at the original TypeScript. `map` is the transform's `map`, made
+/// with `lineOffset`/`columnOffset` for positions in the whole document.
+pub fn map_stack_trace(stack: &str, map: &str, filename: &str) -> Result<MappedStackTrace> {
+  let map = SourceMap::from_slice(map.as_bytes())
+    .map_err(|err| Error::invalid_arg(format!("Invalid source map: {}", err)))?;
+  Ok(map_stack(stack, &map, filename))
+}
@@ -0,0 +1,64 @@
+use crate::{Error, TransformOptions};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+// The browser API, for VS Code for the Web, webviews and playgrounds where the
+// node addon can't load. Same functions and shapes as the napi build; options
+// and outputs cross the boundary through serde.
+
+#[wasm_bindgen(typescript_custom_section)]
+const TYPES: &str = r#"
+import type { TransformOptions, TransformOutput, TransformOutputRegular, EvaluableSpans } from "../index"
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+  #[wasm_bindgen(typescript_type = "TransformOptions")]
+  pub type JsTransformOptions;
+  #[wasm_bindgen(typescript_type = "TransformOutput")]
+  pub type JsTransformOutput;
+  #[wasm_bindgen(typescript_type = "TransformOutputRegular")]
+  pub type JsTransformOutputRegular;
+  #[wasm_bindgen(typescript_type = "EvaluableSpans")]
+  pub type JsEvaluableSpans;
+}
+
+fn options(options: Option<JsTransformOptions>) -> Result<Option<TransformOptions>, JsValue> {
+  match options {
+    Some(options) => serde_wasm_bindgen::from_value(options.into())
+      .map_err(|err| Error::invalid_arg(format!("Invalid options: {}", err)).into()),
+    None => Ok(None),
+  }
+}
+
+fn output<T: Serialize, U: JsCast>(output: &T) -> Result<U, JsValue> {
+  Ok(serde_wasm_bindgen::to_value(output)?.unchecked_into())
+}
+
+#[wasm_bindgen(js_name = "evaluableSpans")]
+pub fn evaluable_spans(
+  source: String,
+  target: u32,
+  opts: Option<JsTransformOptions>,
+) -> Result<JsEvaluableSpans, JsValue> {
+  output(&crate::evaluable_spans(source, target, options(opts)?)?)
+}
+
+/// Performs a transformation on the source string such that its output
+/// is suitable for usage in a REPL environment.
+#[wasm_bindgen(js_name = "transformSync")]
+pub fn transform_sync(
+  source: String,
+  opts: Option<JsTransformOptions>,
+) -> Result<JsTransformOutput, JsValue> {
+  output(&crate::transform_sync(source, options(opts)?)?)
+}
+
+/// Translates TS to JS
+#[wasm_bindgen(js_name = "transformSyncRegular")]
+pub fn transform_sync_regular(
+  source: String,
+  opts: Option<JsTransformOptions>,
+) -> Result<JsTransformOutputRegular, JsValue> {
+  output(&crate::transform_sync_regular(source, options(opts)?)?)
+}
@@ -0,0 +1,285 @@
+use swc_ts_repl_transpile::{
+  evaluable_spans, ErrorKind, Neighbor, NodeKind, Position, Range, TransformOptions,
+};
+
+/// Finds the nodes around the `|` in `source`, which is removed first
+fn neighbors(source: &str) -> Vec<Neighbor> {
+  let target = source.find('|').expect("a | marks the target") as u32;
+  evaluable_spans(source.replacen('|', "", 1), target, None)
+    .unwrap()
+    .spans
+}
+
+/// Finds the spans around the `|` in `source`, which is removed first
+fn spans(source: &str) -> Vec<(u32, u32, NodeKind)> {
+  neighbors(source)
+    .into_iter()
+    .map(|n| (n.start, n.end, n.r#type))
+    .collect()
+}
+
+#[test]
+fn innermost_span_is_last() {
+  let found =
+    spans("export const foo = (a: number, b: number): Promise<E.Either<unknown,never>> =>\n  4|2;");
+  assert_eq!(found.last(), Some(&(82, 84, NodeKind::NumericLiteral)));
+}
+
+#[test]
+fn object_literal() {
+  let found = spans("const what = {|\n    foo: {bar:'baz'},\n    ok: true,\n};");
+  assert_eq!(found.last(), Some(&(14, 53, NodeKind::ObjectExpression)));
+}
+
+#[test]
+fn property_path() {
+  assert_eq!(
+    spans("a.b|.c.d").last(),
+    Some(&(1, 4, NodeKind::MemberExpression))
+  );
+}
+
+#[test]
+fn function_param() {
+  assert_eq!(
+    spans("const f = (a|: number) => {}").last(),
+    Some(&(12, 13, NodeKind::Identifier))
+  );
+}
+
+#[test]
+fn call_expression() {
+  assert_eq!(
+    spans("foo.bar.baz('heyo')|"),
+    vec![
+      (1, 20, NodeKind::ExpressionStatement),
+      (1, 20, NodeKind::CallExpression)
+    ]
+  );
+}
+
+#[test]
+fn invalid_code_has_no_spans() {
+  let output = evaluable_spans("i am invalid code".into(), 14, None).unwrap();
+  assert!(output.spans.is_empty());
+  assert!(output.diagnostics.is_some());
+}
+
+#[test]
+fn statements_that_parse_have_spans_in_invalid_code() {
+  // the call left open doesn't take the declaration after it
+  let source = "const a = [1, 2];\nfoo(\nconst b = a.len|gth;";
+  let found = spans(source);
+  assert_eq!(
+    found.first(),
+    Some(&(24, 43, NodeKind::VariableDeclaration))
+  );
+  assert_eq!(found.last(), Some(&(34, 42, NodeKind::MemberExpression)));
+  assert_eq!(
+    spans("const a = [1, |2];\nfoo(\nconst b = a.length;").last(),
+    Some(&(11, 17, NodeKind::ArrayLiteral))
+  );
+
+  // and the errors are the ones in the chunk that doesn't parse
+  let output = evaluable_spans(source.replacen('|', "", 1), 0, None).unwrap();
+  let errors: Vec<_> = output
+    .diagnostics
+    .unwrap()
+    .into_iter()
+    .filter(|d| d.is_error())
+    .collect();
+  assert!(!errors.is_empty());
+  for error in errors {
+    let span = error.span.unwrap();
+    assert!((18..=23).contains(&span.start), "{:?}", span);
+  }
+}
+
+#[test]
+fn continued_lines_stay_in_their_statement() {
+  let found = spans("foo(\n\nconst b = 1\n.toFixed(|2)");
+  assert_eq!(
+    found.first().map(|n| n.2),
+    Some(NodeKind::VariableDeclaration)
+  );
+}
+
+#[test]
+fn parents_and_depths() {
+  let found: Vec<_> = neighbors("a.b|.c.d")
+    .into_iter()
+    .map(|n| (n.r#type, n.parent, n.depth, n.preview))
+    .collect();
+  assert_eq!(
+    found,
+    vec![
+      (
+        NodeKind::ExpressionStatement,
+        None,
+        0,
+        "a.b.c.d".to_string()
+      ),
+      (
+        NodeKind::MemberExpression,
+        Some(0),
+        1,
+        "a.b.c.d".to_string()
+      ),
+      (NodeKind::MemberExpression, Some(1), 2, "a.b.c".to_string()),
+      (NodeKind::MemberExpression, Some(2), 3, "a.b".to_string()),
+    ]
+  );
+}
+
+#[test]
+fn switch_and_optional_calls_are_labelled_as_such() {
+  let switch = spans("switch (x) { case 1: f|oo(); }");
+  assert_eq!(switch[0].2, NodeKind::SwitchStatement);
+  assert_eq!(switch[1].2, NodeKind::SwitchCase);
+
+  let kinds: Vec<_> = spans("foo?.(1|)").into_iter().map(|n| n.2).collect();
+  assert!(kinds.contains(&NodeKind::OptionalCallExpression));
+  assert!(!kinds.contains(&NodeKind::CallExpression));
+}
+
+#[test]
+fn declarations_are_named() {
+  let named = |source| {
+    neighbors(source)
+      .into_iter()
+      .map(|n| (n.r#type, n.name))
+      .collect::<Vec<_>>()
+  };
+  let found = named("const foo = 4|2;");
+  assert_eq!(
+    found[..2],
+    [
+      (NodeKind::VariableDeclaration, Some("foo".into())),
+      (NodeKind::VariableDeclarator, Some("foo".into())),
+    ]
+  );
+  assert_eq!(found.last(), Some(&(NodeKind::NumericLiteral, None)));
+
+  assert_eq!(
+    named("let a = 1, b = |2;")[..2],
+    [
+      (NodeKind::VariableDeclaration, None),
+      (NodeKind::VariableDeclarator, Some("b".into())),
+    ]
+  );
+  assert_eq!(
+    named("export function bar() { return |1 }")[0],
+    (NodeKind::ModuleDeclaration, Some("bar".into()))
+  );
+  assert!(
+    named("import {a as b|} from 'm'").contains(&(NodeKind::ImportSpecifier, Some("b".into())))
+  );
+}
+
+#[test]
+fn previews_are_one_short_line() {
+  let found = neighbors("const what = {|\n    foo: {bar:'baz'},\n    ok: true,\n};");
+  assert_eq!(
+    found.last().unwrap().preview,
+    "{ foo: {bar:'baz'}, ok: true, }"
+  );
+
+  let long = format!("[{}|]", "1, ".repeat(30));
+  let found = neighbors(&long);
+  let preview = &found.last().unwrap().preview;
+  assert_eq!(preview.chars().count(), 40);
+  assert!(preview.starts_with("[1, 1, "));
+  assert!(preview.ends_with('…'));
+}
+
+fn encoded(encoding: &str) -> Option<TransformOptions> {
+  Some(TransformOptions {
+    offset_encoding: Some(encoding.into()),
+    ..Default::default()
+  })
+}
+
+#[test]
+fn utf16_offsets_count_code_units() {
+  // "é" is 2 bytes and 1 code unit, "😀" 4 bytes and 2 code units
+  let source = "const s = \"é😀\"; foo(1)";
+  let output = evaluable_spans(source.into(), 22, encoded("utf16")).unwrap();
+  let found: Vec<_> = output
+    .spans
+    .iter()
+    .map(|n| (n.start, n.end, n.r#type))
+    .collect();
+  assert_eq!(
+    found,
+    vec![
+      (17, 23, NodeKind::ExpressionStatement),
+      (17, 23, NodeKind::CallExpression),
+      (21, 22, NodeKind::NumericLiteral),
+    ]
+  );
+
+  let output = evaluable_spans(source.into(), 25, encoded("bytes")).unwrap();
+  assert_eq!((output.spans[0].start, output.spans[0].end), (20, 26));
+}
+
+#[test]
+fn line_column_targets_and_ranges() {
+  let source = "const s = \"é😀\";\r\nfoo(1)";
+  let cursor = Position {
+    line: 1,
+    character: 5,
+  };
+  let output = evaluable_spans(source.into(), cursor, encoded("lineColumn")).unwrap();
+  let statement = &output.spans[0];
+  assert_eq!(statement.r#type, NodeKind::ExpressionStatement);
+  assert_eq!((statement.start, statement.end), (18, 24));
+  assert_eq!(
+    statement.range,
+    Some(Range {
+      start: Position {
+        line: 1,
+        character: 0
+      },
+      end: Position {
+        line: 1,
+        character: 6
+      },
+    })
+  );
+
+  // positions work with the other encodings too, without ranges
+  let output = evaluable_spans(source.into(), cursor, encoded("utf16")).unwrap();
+  assert_eq!((output.spans[0].start, output.spans[0].end), (18, 24));
+  assert_eq!(output.spans[0].range, None);
+
+  let err = evaluable_spans(source.into(), 20, encoded("lineColumn"))
+    .err()
+    .unwrap();
+  assert_eq!(err.kind, ErrorKind::InvalidArg);
+}
+
+#[test]
+fn bom_is_not_part_of_the_document() {
+  let output = evaluable_spans("\u{feff}foo(1)".into(), 5, encoded("bytes")).unwrap();
+  assert_eq!((output.spans[0].start, output.spans[0].end), (0, 6));
+}
+
+#[test]
+fn diagnostics_use_the_encoding() {
+  let source = "const s = \"😀\"; i am invalid code";
+  let output = evaluable_spans(source.into(), 0, encoded("utf16")).unwrap();
+  for diagnostic in output.diagnostics.unwrap() {
+    let span = diagnostic.span.unwrap();
+    assert_eq!(span.start_line, 1);
+    assert_eq!(span.start_column, span.start);
+    assert!(span.start >= 16 && span.end <= source.encode_utf16().count() as u32);
+  }
+}
+
+#[test]
+fn unknown_encodings_are_rejected() {
+  let err = evaluable_spans("1".into(), 0, encoded("utf8"))
+    .err()
+    .unwrap();
+  assert_eq!(err.kind, ErrorKind::InvalidArg);
+}
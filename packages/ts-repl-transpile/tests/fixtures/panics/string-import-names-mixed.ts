@@ -0,0 +1,2 @@
+import {"default" as d, "x y" as xy, z} from "m";
+console.log(d, xy, z);
@@ -0,0 +1,3 @@
+const enum E { A = 1 }
+let y: E;
+await E.A;
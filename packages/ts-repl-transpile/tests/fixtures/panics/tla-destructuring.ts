@@ -0,0 +1,2 @@
+const {a, ...rest} = await Promise.resolve({a: 1, b: 2});
+const [first, , ...others] = await Promise.resolve([1, 2, 3]);
@@ -0,0 +1,2 @@
+export * from "m";
+await 1;
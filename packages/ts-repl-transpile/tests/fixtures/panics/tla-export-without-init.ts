@@ -0,0 +1,2 @@
+export let x;
+await 1;
@@ -0,0 +1,2 @@
+let x;
+await Promise.resolve(1);
@@ -0,0 +1,2 @@
+let a, b = await Promise.resolve(2), c;
+const d = 1, e = await Promise.resolve(d);
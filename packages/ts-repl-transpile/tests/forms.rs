@@ -0,0 +1,230 @@
+use swc_ts_repl_transpile::{
+  evaluable_selections, select_forms, NodeKind, Position, SelectedForms, Selection,
+  TransformOptions,
+};
+
+fn bytes() -> Option<TransformOptions> {
+  Some(TransformOptions {
+    offset_encoding: Some("bytes".into()),
+    ..Default::default()
+  })
+}
+
+/// The forms around the selection between `[` and `]`, or the cursor at `|`,
+/// which are removed first
+fn select(source: &str) -> SelectedForms {
+  let selection: Selection = match source.find('|') {
+    Some(cursor) => (cursor as u32).into(),
+    None => {
+      let start = source.find('[').expect("a selection or cursor") as u32;
+      let end = source.find(']').expect("the end of the selection") as u32 - 1;
+      (start, end).into()
+    }
+  };
+  let source = source.replace(['|', '[', ']'], "");
+  select_forms(source, selection, bytes()).unwrap()
+}
+
+/// Where the span at `index` is and what it is
+fn form(forms: &SelectedForms, index: Option<u32>) -> Option<(u32, u32, NodeKind)> {
+  let span = &forms.spans[index? as usize];
+  Some((span.start, span.end, span.r#type))
+}
+
+#[test]
+fn cursor_expands_to_the_form_before_it() {
+  let forms = select("let x = 1; foo(1)|");
+  assert_eq!(
+    form(&forms, forms.expand),
+    Some((11, 17, NodeKind::CallExpression))
+  );
+  assert_eq!(form(&forms, forms.shrink), None);
+  assert_eq!(
+    form(&forms, forms.top_level),
+    Some((11, 17, NodeKind::ExpressionStatement))
+  );
+  assert_eq!(
+    form(&forms, forms.innermost_expression),
+    Some((11, 17, NodeKind::CallExpression))
+  );
+}
+
+#[test]
+fn selection_expands_and_shrinks() {
+  let forms = select("[a.b].c.d");
+  assert_eq!(
+    form(&forms, forms.expand),
+    Some((0, 5, NodeKind::MemberExpression))
+  );
+  assert_eq!(
+    form(&forms, forms.shrink),
+    Some((0, 1, NodeKind::Identifier))
+  );
+  assert_eq!(
+    form(&forms, forms.innermost_expression),
+    Some((0, 3, NodeKind::MemberExpression))
+  );
+
+  // forms the same size as the selection are skipped
+  let forms = select("[foo(1)];");
+  assert_eq!(
+    form(&forms, forms.expand),
+    Some((0, 7, NodeKind::ExpressionStatement))
+  );
+}
+
+#[test]
+fn top_level_form_holds_the_cursor() {
+  let forms = select("function f() {\n  return g(1|);\n}\nf();");
+  let (start, end, _) = form(&forms, forms.top_level).unwrap();
+  assert_eq!((start, end), (0, 31));
+  assert_eq!(
+    form(&forms, forms.innermost_expression),
+    Some((26, 27, NodeKind::NumericLiteral))
+  );
+}
+
+#[test]
+fn selections_across_forms_have_no_top_level_form() {
+  let forms = select("[a; b]");
+  assert_eq!(forms.top_level, None);
+  assert_eq!(forms.expand, None);
+  assert_eq!(
+    form(&forms, forms.shrink),
+    Some((0, 2, NodeKind::ExpressionStatement))
+  );
+}
+
+#[test]
+fn selections_go_either_way() {
+  let forward = select_forms("a.b.c".into(), (0u32, 3u32), bytes()).unwrap();
+  let backward = select_forms("a.b.c".into(), (3u32, 0u32), bytes()).unwrap();
+  assert_eq!(
+    form(&forward, forward.expand),
+    form(&backward, backward.expand)
+  );
+  assert_eq!(
+    form(&forward, forward.shrink),
+    form(&backward, backward.shrink)
+  );
+}
+
+#[test]
+fn positions_select_too() {
+  let options = Some(TransformOptions {
+    offset_encoding: Some("lineColumn".into()),
+    ..Default::default()
+  });
+  let position = |line, character| Position { line, character };
+  let forms = select_forms(
+    "let x = 1;\nfoo(x)".into(),
+    (position(1, 0), position(1, 3)),
+    options,
+  )
+  .unwrap();
+  let expand = &forms.spans[forms.expand.unwrap() as usize];
+  assert_eq!(expand.r#type, NodeKind::CallExpression);
+  assert_eq!(
+    expand.range.map(|range| (range.start, range.end)),
+    Some((position(1, 0), position(1, 6)))
+  );
+}
+
+/// The start, end and kind of each form covering a selection, and whether
+/// they cut through a form
+type Covered = (Vec<(u32, u32, NodeKind)>, bool);
+
+/// The forms covering each selection between `«` and `»`, and each cursor at
+/// `|`
+fn cover(source: &str) -> Vec<Covered> {
+  let mut code = String::new();
+  let mut selections: Vec<Selection> = vec![];
+  let mut start = 0;
+  for c in source.chars() {
+    let at = code.len() as u32;
+    match c {
+      '«' => start = at,
+      '»' => selections.push((start, at).into()),
+      '|' => selections.push(at.into()),
+      c => code.push(c),
+    }
+  }
+  evaluable_selections(code, selections, bytes())
+    .unwrap()
+    .selections
+    .into_iter()
+    .map(|covered| {
+      let forms = covered
+        .forms
+        .iter()
+        .map(|n| (n.start, n.end, n.r#type))
+        .collect();
+      (forms, covered.cuts_form)
+    })
+    .collect()
+}
+
+#[test]
+fn selections_snap_to_whole_statements() {
+  use NodeKind::VariableDeclaration;
+  let whole = vec![(0, 10, VariableDeclaration), (11, 21, VariableDeclaration)];
+  assert_eq!(
+    cover("«let a = 1;\nlet b = 2;»\nlet c = 3;"),
+    vec![(whole.clone(), false)]
+  );
+  assert_eq!(
+    cover("«let a = 1;\nlet b = 2;\n»let c = 3;"),
+    vec![(whole.clone(), false)]
+  );
+  assert_eq!(
+    cover("let «a = 1;\nlet b» = 2;\nlet c = 3;"),
+    vec![(whole, true)]
+  );
+}
+
+#[test]
+fn selections_skip_comments_between_forms() {
+  use NodeKind::VariableDeclaration;
+  assert_eq!(
+    cover("«let a = 1;\n// one\n/* two */ let b = 2;»"),
+    vec![(
+      vec![(0, 10, VariableDeclaration), (28, 38, VariableDeclaration)],
+      false
+    )]
+  );
+}
+
+#[test]
+fn selections_snap_to_the_children_of_the_form_holding_them() {
+  assert_eq!(
+    cover("[1, «2, 3»]"),
+    vec![(
+      vec![
+        (4, 5, NodeKind::NumericLiteral),
+        (7, 8, NodeKind::NumericLiteral)
+      ],
+      false
+    )]
+  );
+  // nothing but the operator needs the whole expression
+  assert_eq!(
+    cover("1 «+» 2"),
+    vec![(vec![(0, 5, NodeKind::BinaryExpression)], true)]
+  );
+  assert_eq!(
+    cover("«foo(1)»"),
+    vec![(vec![(0, 6, NodeKind::CallExpression)], false)]
+  );
+}
+
+#[test]
+fn each_cursor_and_selection_is_covered() {
+  assert_eq!(
+    cover("foo(1)|;\n«bar»();\n« »"),
+    vec![
+      (vec![(0, 6, NodeKind::CallExpression)], false),
+      (vec![(8, 11, NodeKind::Identifier)], false),
+      (vec![], false),
+    ]
+  );
+}
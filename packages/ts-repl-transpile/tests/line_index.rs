@@ -0,0 +1,48 @@
+use swc_ts_repl_transpile::line_index::{LineIndex, Position};
+
+fn pos(line: u32, character: u32) -> Position {
+  Position { line, character }
+}
+
+#[test]
+fn counts_utf16_code_units() {
+  // 'é' is 2 bytes and 1 unit, '😀' 4 bytes and 2 units
+  let index = LineIndex::new("é😀x");
+  assert_eq!(index.utf16(0), 0);
+  assert_eq!(index.utf16(2), 1);
+  assert_eq!(index.utf16(6), 3);
+  assert_eq!(index.offset_of_utf16(3), 6);
+  // the middle of the surrogate pair is the start of the emoji
+  assert_eq!(index.offset_of_utf16(2), 2);
+  assert_eq!(index.position(6), pos(0, 3));
+}
+
+#[test]
+fn crlf_and_lone_cr_end_lines() {
+  let index = LineIndex::new("a\r\nb\rc\nd");
+  assert_eq!(index.position(3), pos(1, 0));
+  assert_eq!(index.position(5), pos(2, 0));
+  assert_eq!(index.position(7), pos(3, 0));
+  assert_eq!(index.utf16(3), 3);
+  assert_eq!(index.offset_of_position(pos(1, 0)), 3);
+  // past the end of a line is before its line break
+  assert_eq!(index.offset_of_position(pos(0, 5)), 1);
+  assert_eq!(index.offset_of_position(pos(9, 0)), 8);
+}
+
+#[test]
+fn bom_is_not_part_of_the_document() {
+  let index = LineIndex::new("\u{feff}ab");
+  assert_eq!(index.with_bom(0), 3);
+  assert_eq!(index.without_bom(4), 1);
+  assert_eq!(index.position(1), pos(0, 1));
+  assert_eq!(index.utf16(1), 1);
+}
+
+#[test]
+fn before_the_cursor() {
+  let index = LineIndex::new("aé");
+  assert_eq!(index.before(0), 0);
+  assert_eq!(index.before(1), 0);
+  assert_eq!(index.before(3), 1);
+}
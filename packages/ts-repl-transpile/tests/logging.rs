@@ -0,0 +1,36 @@
+use log::LevelFilter;
+use std::sync::{Arc, Mutex};
+use swc_ts_repl_transpile::logging::{clear_log_sink, parse_level, set_log_sink};
+use swc_ts_repl_transpile::{transform_sync, ErrorKind};
+
+// One test, since the sink is global to the process
+#[test]
+fn forwards_records_to_the_sink() {
+  let records = Arc::new(Mutex::new(Vec::new()));
+  let sink = records.clone();
+  set_log_sink(LevelFilter::Debug, move |record| {
+    sink.lock().unwrap().push((
+      record.level(),
+      record.target().to_string(),
+      record.args().to_string(),
+    ))
+  })
+  .unwrap();
+
+  transform_sync("1".into(), None).unwrap();
+  assert!(records.lock().unwrap().contains(&(
+    log::Level::Debug,
+    "swc_ts_repl_transpile::pipeline".into(),
+    "running pass stripTypes".into()
+  )));
+
+  records.lock().unwrap().clear();
+  set_log_sink(LevelFilter::Warn, |_| panic!("replaced sink was called")).unwrap();
+  transform_sync("1".into(), None).unwrap();
+  clear_log_sink();
+  transform_sync("1".into(), None).unwrap();
+  assert!(records.lock().unwrap().is_empty());
+
+  assert_eq!(parse_level("TRACE").unwrap(), LevelFilter::Trace);
+  assert_eq!(parse_level("loud").unwrap_err().kind, ErrorKind::InvalidArg);
+}
@@ -0,0 +1,140 @@
+use std::fs;
+use std::path::PathBuf;
+use swc_core::ecma::ast::Module;
+use swc_ts_repl_transpile::{
+  evaluable_spans, explain_transform, transform_sync, transform_sync_regular,
+  transform_with_pipeline, Diagnostic, ErrorKind, ModuleOptions, PassContext, Pipeline, ReplPass,
+  ReplSession, Result, TransformOptions,
+};
+
+/// Inputs that panicked, and their neighbours, one per file in
+/// `tests/fixtures/panics`
+fn corpus() -> Vec<(String, String)> {
+  let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/panics");
+  let mut inputs: Vec<_> = fs::read_dir(dir)
+    .unwrap()
+    .map(|entry| {
+      let path = entry.unwrap().path();
+      let name = path.file_name().unwrap().to_string_lossy().into_owned();
+      (name, fs::read_to_string(&path).unwrap())
+    })
+    .collect();
+  inputs.sort();
+  inputs
+}
+
+fn option_sets() -> Vec<Option<TransformOptions>> {
+  vec![
+    None,
+    Some(TransformOptions {
+      module: Some(ModuleOptions {
+        r#type: Some("es".into()),
+        ..Default::default()
+      }),
+      ..Default::default()
+    }),
+    Some(TransformOptions {
+      source_maps: Some(true),
+      filename: Some("corpus.ts".into()),
+      ..Default::default()
+    }),
+  ]
+}
+
+fn has_errors(diagnostics: &Option<Vec<Diagnostic>>) -> bool {
+  diagnostics
+    .as_ref()
+    .is_some_and(|d| d.iter().any(Diagnostic::is_error))
+}
+
+/// Parses emitted code again, since passes that build the AST by hand can
+/// print code that only looks right, like `{a} = b;`
+fn assert_reparses(name: &str, code: &str) {
+  let output = transform_sync_regular(
+    code.into(),
+    Some(TransformOptions {
+      syntax: Some("ecmascript".into()),
+      ..Default::default()
+    }),
+  )
+  .unwrap();
+  assert!(
+    !has_errors(&output.diagnostics),
+    "{} emitted code that doesn't parse: {:?}\n{}",
+    name,
+    output.diagnostics,
+    code
+  );
+}
+
+#[test]
+fn corpus_transforms_without_panicking() {
+  for (name, source) in corpus() {
+    for options in option_sets() {
+      let ok = |result: Result<()>| {
+        if let Err(err) = result {
+          assert_ne!(err.kind, ErrorKind::Internal, "{}: {}", name, err);
+        }
+      };
+      // what parsed without errors has to come out as valid JavaScript
+      let emits = |result: Result<(String, Option<Vec<Diagnostic>>)>| match result {
+        Ok((code, diagnostics)) if !has_errors(&diagnostics) => assert_reparses(&name, &code),
+        result => ok(result.map(drop)),
+      };
+      emits(transform_sync(source.clone(), options.clone()).map(|o| (o.code, o.diagnostics)));
+      emits(
+        transform_sync_regular(source.clone(), options.clone()).map(|o| (o.code, o.diagnostics)),
+      );
+      emits(
+        explain_transform(source.clone(), options.clone(), false)
+          .map(|o| (o.output.code, o.output.diagnostics)),
+      );
+      emits(
+        ReplSession::new()
+          .transform("ns".into(), source.clone(), options.clone())
+          .map(|o| (o.code, o.diagnostics)),
+      );
+      for target in 0..=source.len() as u32 {
+        ok(evaluable_spans(source.clone(), target, options.clone()).map(drop));
+      }
+    }
+  }
+}
+
+#[test]
+fn top_level_await_keeps_every_declarator() {
+  let output = transform_sync("const a = 1, b = await Promise.resolve(a);".into(), None).unwrap();
+  assert!(output.code.contains("a = 1"), "{}", output.code);
+  assert!(output.code.contains("b = await"), "{}", output.code);
+}
+
+#[test]
+fn string_import_names_are_destructured() {
+  let output = transform_sync(r#"import {"a-b" as ab} from "m"; ab"#.into(), None).unwrap();
+  assert!(output.code.contains(r#""a-b": ab"#), "{}", output.code);
+}
+
+struct Panics;
+
+impl ReplPass for Panics {
+  fn name(&self) -> &str {
+    "panics"
+  }
+
+  fn run(&self, _module: Module, _ctx: &mut PassContext) -> Result<Module> {
+    panic!("this pass always panics")
+  }
+}
+
+#[test]
+fn panicking_passes_become_internal_errors() {
+  let mut pipeline = Pipeline::default();
+  pipeline.push(Panics);
+  let err = transform_with_pipeline("1".into(), None, &pipeline)
+    .err()
+    .unwrap();
+  assert_eq!(err.kind, ErrorKind::Internal);
+  assert!(err.message.starts_with("The panics pass panicked at "));
+  assert!(err.message.contains("tests/panics.rs"));
+  assert!(err.message.ends_with(": this pass always panics"));
+}
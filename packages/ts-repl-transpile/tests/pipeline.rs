@@ -0,0 +1,174 @@
+use swc_core::ecma::ast::{Module, ModuleItem, Stmt};
+use swc_ts_repl_transpile::ast_hook::{self, AstHook};
+use swc_ts_repl_transpile::{
+  explain_transform, transform_with_pipeline, ErrorKind, PassContext, Pipeline, ReplPass,
+  ReplSession, Result,
+};
+
+/// Drops every statement, to see where in the pipeline it ran
+struct DropStatements;
+
+impl ReplPass for DropStatements {
+  fn name(&self) -> &str {
+    "dropStatements"
+  }
+
+  fn run(&self, mut module: Module, _ctx: &mut PassContext) -> Result<Module> {
+    module
+      .body
+      .retain(|item| !matches!(item, ModuleItem::Stmt(Stmt::Expr(_))));
+    Ok(module)
+  }
+
+  fn after_emit(&self, code: &mut String, _ctx: &mut PassContext) -> Result<()> {
+    code.push_str("// dropped\n");
+    Ok(())
+  }
+}
+
+fn transform(source: &str, pipeline: &Pipeline) -> String {
+  transform_with_pipeline(source.into(), None, pipeline)
+    .unwrap()
+    .code
+}
+
+#[test]
+fn default_pipeline_runs_the_repl_passes_in_order() {
+  assert_eq!(
+    Pipeline::default().names(),
+    vec![
+      "inlineConstEnums",
+      "exportStarHelper",
+      "importRewrite",
+      "decorators",
+      "stripTypes",
+      "react",
+      "esmExports",
+      "commonJs",
+      "injectHelpers",
+      "allToVar",
+      "exportsConfigurable",
+      "topLevelAwait",
+    ]
+  );
+}
+
+#[test]
+fn disabled_passes_are_skipped() {
+  let mut pipeline = Pipeline::default();
+  pipeline.set_enabled("allToVar", false).unwrap();
+  assert!(!pipeline.is_enabled("allToVar").unwrap());
+  assert_eq!(transform("const a = 1;", &pipeline), "const a = 1;\n");
+}
+
+#[test]
+fn added_passes_run_where_they_are_inserted() {
+  let mut pipeline = Pipeline::default();
+  pipeline
+    .insert_before("topLevelAwait", DropStatements)
+    .unwrap();
+  assert_eq!(
+    transform("const a = 1; a;", &pipeline),
+    "var a = 1;\n// dropped\n"
+  );
+
+  pipeline
+    .move_before("dropStatements", "inlineConstEnums")
+    .unwrap();
+  assert_eq!(pipeline.names()[0], "dropStatements");
+  pipeline.remove("dropStatements").unwrap();
+  assert_eq!(pipeline.names(), Pipeline::default().names());
+}
+
+#[test]
+fn unknown_pass_names_are_errors() {
+  let mut pipeline = Pipeline::default();
+  let err = pipeline.set_enabled("nope", false).unwrap_err();
+  assert_eq!(err.kind, ErrorKind::InvalidArg);
+  assert!(pipeline.move_before("allToVar", "nope").is_err());
+  assert_eq!(pipeline.names(), Pipeline::default().names());
+}
+
+#[test]
+fn sessions_use_their_pipeline() {
+  let mut pipeline = Pipeline::default();
+  pipeline.set_enabled("allToVar", false).unwrap();
+  let mut session = ReplSession::with_pipeline(pipeline);
+  let output = session
+    .transform("ns".into(), "let a = 1;".into(), None)
+    .unwrap();
+  assert_eq!(output.code, "let a = 1;\n");
+  assert_eq!(session.bindings("ns".into()), vec!["a"]);
+}
+
+#[test]
+fn restored_sessions_run_the_default_pipeline_until_given_theirs() {
+  let mut pipeline = Pipeline::default();
+  pipeline.set_enabled("allToVar", false).unwrap();
+  let session = ReplSession::with_pipeline(pipeline.clone());
+  let mut restored = ReplSession::from_json(session.to_json().unwrap()).unwrap();
+  let output = restored
+    .transform("ns".into(), "let a = 1;".into(), None)
+    .unwrap();
+  assert_eq!(output.code, "var a = 1;\n");
+
+  restored.set_pipeline(pipeline);
+  let output = restored
+    .transform("ns".into(), "let b = 1;".into(), None)
+    .unwrap();
+  assert_eq!(output.code, "let b = 1;\n");
+}
+
+#[test]
+fn ast_hooks_get_and_return_json() {
+  let mut pipeline = Pipeline::default();
+  let hook = AstHook::new(|json| {
+    assert!(json.contains("\"type\":\"Module\""));
+    Ok(Some(
+      json
+        .replace("\"value\":1.0", "\"value\":2.0")
+        .replace("\"raw\":\"1\"", "\"raw\":\"2\""),
+    ))
+  });
+  ast_hook::insert(&mut pipeline, ast_hook::DEFAULT_STAGE, hook).unwrap();
+  assert_eq!(pipeline.names()[5], "astHook");
+  assert_eq!(transform("const a = 1;", &pipeline), "var a = 2;\n");
+}
+
+#[test]
+fn modules_round_trip_through_json() {
+  let source = "class A<T> { #x = `${1}`; async *m(a?: T) { yield* [a]; } }\nlabel: for (const [k, ...v] of new Map()) break label;";
+  let mut pipeline = Pipeline::default();
+  let hook = AstHook::new(|json| Ok(Some(json)));
+  ast_hook::insert(&mut pipeline, "parse", hook).unwrap();
+  assert_eq!(
+    transform(source, &pipeline),
+    transform(source, &Pipeline::default())
+  );
+}
+
+#[test]
+fn ast_hooks_can_run_on_the_parsed_module() {
+  let mut pipeline = Pipeline::default();
+  ast_hook::insert(&mut pipeline, "parse", AstHook::new(|_| Ok(None))).unwrap();
+  assert_eq!(pipeline.names()[0], "astHook");
+  assert_eq!(transform("const a = 1;", &pipeline), "var a = 1;\n");
+  assert!(ast_hook::module_from_json("{}").is_err());
+}
+
+#[test]
+fn explain_keeps_the_code_after_each_stage() {
+  let explained = explain_transform("let a = 1;".into(), None, false).unwrap();
+  assert_eq!(explained.output.code, "var a = 1;\n");
+  let names: Vec<_> = explained.stages.iter().map(|s| s.name.as_str()).collect();
+  assert_eq!(names[0], "parse");
+  assert_eq!(&names[1..], Pipeline::default().names().as_slice());
+  let all_to_var = explained
+    .stages
+    .iter()
+    .find(|s| s.name == "allToVar")
+    .unwrap();
+  assert!(all_to_var.changed);
+  assert_eq!(all_to_var.code, "var a = 1;\n");
+  assert_eq!(explained.stages[0].code, "let a = 1;\n");
+}
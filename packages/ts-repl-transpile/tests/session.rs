@@ -0,0 +1,138 @@
+use swc_ts_repl_transpile::{map_stack_trace, ErrorKind, ReplSession};
+
+#[test]
+fn records_bindings_and_redeclarations() {
+  let mut session = ReplSession::new();
+  let first = session
+    .transform("ns".into(), "const a = 1; function f() {}".into(), None)
+    .unwrap();
+  assert_eq!(first.declared, vec!["a", "f"]);
+  assert!(first.redeclared.is_empty());
+
+  let second = session
+    .transform("ns".into(), "let a = 2;".into(), None)
+    .unwrap();
+  assert_eq!(second.redeclared, vec!["a"]);
+  assert_eq!(session.bindings("ns".into()), vec!["a", "f"]);
+  assert!(session.bindings("other".into()).is_empty());
+}
+
+#[test]
+fn failed_transforms_record_nothing() {
+  let mut session = ReplSession::new();
+  let output = session
+    .transform("ns".into(), "const a = ;".into(), None)
+    .unwrap();
+  assert!(output.diagnostics.is_some());
+  assert!(session.bindings("ns".into()).is_empty());
+  assert!(session.history("ns".into()).is_empty());
+}
+
+#[test]
+fn inlines_const_enums_from_earlier_evaluations() {
+  let mut session = ReplSession::new();
+  session
+    .transform("ns".into(), "const enum E { A = 1, B }".into(), None)
+    .unwrap();
+  let enums = session.const_enums("ns".into());
+  let members: Vec<_> = enums[0]
+    .members
+    .iter()
+    .map(|m| (m.name.as_str(), m.value.as_f64()))
+    .collect();
+  assert_eq!(members, vec![("A", Some(1.0)), ("B", Some(2.0))]);
+
+  let output = session.transform("ns".into(), "E.B".into(), None).unwrap();
+  assert!(output.code.starts_with("2;"), "{}", output.code);
+}
+
+#[test]
+fn const_enums_are_not_inlined_where_something_else_is_called_that() {
+  let mut session = ReplSession::new();
+  session
+    .transform("ns".into(), "const enum E { A = 1 }".into(), None)
+    .unwrap();
+  let source = "function f(E) { return E.A; }\n{ let E = {}; E.A; }\nE.A;";
+  let output = session.transform("ns".into(), source.into(), None).unwrap();
+  assert_eq!(output.code.matches("E.A").count(), 2, "{}", output.code);
+  assert!(output.code.ends_with("1;\n"), "{}", output.code);
+}
+
+#[test]
+fn export_star_helper_is_defined_once() {
+  let mut session = ReplSession::new();
+  let first = session
+    .transform("ns".into(), "export * from 'a';".into(), None)
+    .unwrap();
+  let second = session
+    .transform("ns".into(), "export * from 'b';".into(), None)
+    .unwrap();
+  assert!(first.code.contains("function _exportStar("));
+  assert!(!second.code.contains("function _exportStar("));
+  assert_eq!(session.helpers("ns".into()), vec!["exportStar"]);
+}
+
+#[test]
+fn unmap_and_reset_forget_state() {
+  let mut session = ReplSession::new();
+  session
+    .transform("a".into(), "const x = 1, y = 2;".into(), None)
+    .unwrap();
+  session
+    .transform("b".into(), "const z = 1;".into(), None)
+    .unwrap();
+  session.unmap("a".into(), "x".into());
+  assert_eq!(session.bindings("a".into()), vec!["y"]);
+  session.reset(Some("a".into()));
+  assert_eq!(session.namespaces(), vec!["b"]);
+  session.reset(None);
+  assert!(session.namespaces().is_empty());
+}
+
+#[test]
+fn json_round_trip() {
+  let mut session = ReplSession::new();
+  session
+    .transform("ns".into(), "const enum E { A } const a = 1;".into(), None)
+    .unwrap();
+  let restored = ReplSession::from_json(session.to_json().unwrap()).unwrap();
+  assert_eq!(restored.namespaces(), vec!["ns"]);
+  assert_eq!(restored.history("ns".into()).len(), 1);
+  // those were defined in a context that is gone
+  assert!(restored.bindings("ns".into()).is_empty());
+  assert!(restored.const_enums("ns".into()).is_empty());
+
+  let err = ReplSession::from_json("{\"version\": 999}".into())
+    .err()
+    .unwrap();
+  assert_eq!(err.kind, ErrorKind::InvalidArg);
+}
+
+#[test]
+fn restored_sessions_define_helpers_again() {
+  let mut session = ReplSession::new();
+  session
+    .transform("ns".into(), "export * from 'a';".into(), None)
+    .unwrap();
+  let path = std::env::temp_dir().join(format!("ts-repl-session-{}.json", std::process::id()));
+  let path = path.to_string_lossy().into_owned();
+  session.save(path.clone()).unwrap();
+  let mut restored = ReplSession::load(path.clone()).unwrap();
+  std::fs::remove_file(&path).unwrap();
+
+  assert!(restored.helpers("ns".into()).is_empty());
+  let output = restored
+    .transform("ns".into(), "export * from 'x';".into(), None)
+    .unwrap();
+  assert!(
+    output.code.contains("function _exportStar("),
+    "{}",
+    output.code
+  );
+}
+
+#[test]
+fn invalid_source_maps_are_rejected() {
+  let err = map_stack_trace("Error\n    at a.ts:1:1", "not a map", "a.ts").unwrap_err();
+  assert_eq!(err.kind, ErrorKind::InvalidArg);
+}
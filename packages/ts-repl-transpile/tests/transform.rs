@@ -0,0 +1,171 @@
+use swc_ts_repl_transpile::{
+  transform_sync, transform_sync_regular, ErrorKind, ModuleOptions, PluginOptions, TransformOptions,
+};
+
+fn repl(source: &str) -> String {
+  let output = transform_sync(source.into(), None).unwrap();
+  assert!(output.diagnostics.is_none(), "{:?}", output.diagnostics);
+  output.code
+}
+
+#[test]
+fn rewrites_named_imports_to_require() {
+  assert_eq!(
+    repl("import {foo} from 'bar';foo"),
+    "var { foo  } = require(\"bar\");\nfoo;\nfoo;\n"
+  );
+  assert_eq!(
+    repl("import {foo} from 'bar';"),
+    "var { foo  } = require(\"bar\");\nfoo;\n"
+  );
+}
+
+#[test]
+fn strips_types_and_declares_with_var() {
+  assert_eq!(repl("const foo: number = 42;"), "var foo = 42;\n");
+  assert_eq!(repl("let foo = 1;"), "var foo = 1;\n");
+}
+
+#[test]
+fn never_emits_use_strict() {
+  assert!(!repl("export const foo = 42;").starts_with("\"use strict\""));
+}
+
+#[test]
+fn export_star_helper_is_appended() {
+  let code = repl("export * from 'foo';");
+  assert!(code.contains("function _exportStar("), "{}", code);
+}
+
+#[test]
+fn top_level_await_runs_in_an_async_iife() {
+  let output = transform_sync("const foo = async () => 42;\n  await foo()".into(), None).unwrap();
+  assert!(output.is_async);
+  assert_eq!(
+    output.code,
+    "var foo;\n(async ()=>{\n    foo = async ()=>42;\n    return await foo();\n})();\n"
+  );
+}
+
+#[test]
+fn no_await_is_not_async() {
+  assert!(
+    !transform_sync("const foo = 42;".into(), None)
+      .unwrap()
+      .is_async
+  );
+}
+
+#[test]
+fn invalid_source_is_reported_as_diagnostics() {
+  let output = transform_sync("const = ;".into(), None).unwrap();
+  let diagnostics = output.diagnostics.expect("diagnostics");
+  assert!(diagnostics.iter().any(|d| d.is_error()));
+  assert!(diagnostics[0].span.is_some());
+}
+
+#[test]
+fn invalid_options_are_errors() {
+  let err = transform_sync(
+    "1".into(),
+    Some(TransformOptions {
+      target: Some("es1999".into()),
+      ..Default::default()
+    }),
+  )
+  .err()
+  .unwrap();
+  assert_eq!(err.kind, ErrorKind::InvalidArg);
+}
+
+#[test]
+fn source_maps_are_opt_in() {
+  assert!(transform_sync("1".into(), None).unwrap().map.is_none());
+  let output = transform_sync(
+    "const a: number = 1".into(),
+    Some(TransformOptions {
+      source_maps: Some(true),
+      filename: Some("/tmp/a.ts".into()),
+      ..Default::default()
+    }),
+  )
+  .unwrap();
+  let map = output.map.expect("map");
+  assert!(map.contains("/tmp/a.ts"), "{}", map);
+}
+
+#[test]
+fn es_modules_export_every_binding() {
+  let output = transform_sync(
+    "const a = 1; function f() {}".into(),
+    Some(TransformOptions {
+      module: Some(ModuleOptions {
+        r#type: Some("es".into()),
+        ..Default::default()
+      }),
+      ..Default::default()
+    }),
+  )
+  .unwrap();
+  assert!(output.code.contains("export { a, f };"), "{}", output.code);
+}
+
+#[test]
+fn regular_transform_only_strips_types() {
+  let output = transform_sync_regular(
+    "const foo = async (): Promise<number> => 42;\n  await foo()".into(),
+    None,
+  )
+  .unwrap();
+  assert_eq!(output.code, "const foo = async ()=>42;\nawait foo();\n");
+}
+
+#[test]
+fn plugins_that_cannot_run_are_errors() {
+  let result = transform_sync(
+    "const a = 1".into(),
+    Some(TransformOptions {
+      plugins: Some(vec![PluginOptions {
+        path: "/does/not/exist.wasm".into(),
+        config: None,
+      }]),
+      ..Default::default()
+    }),
+  );
+  assert!(result.is_err());
+}
+
+fn in_file(filename: &str) -> Option<TransformOptions> {
+  Some(TransformOptions {
+    filename: Some(filename.into()),
+    ..Default::default()
+  })
+}
+
+#[test]
+fn classic_jsx_keeps_the_import_of_its_pragma() {
+  let source = "import React from 'react';\nconst el = <div />;";
+  let output = transform_sync(source.into(), in_file("view.tsx")).unwrap();
+  assert!(
+    output.code.contains("require(\"react\")"),
+    "{}",
+    output.code
+  );
+  let output = transform_sync_regular(source.into(), in_file("view.tsx")).unwrap();
+  assert!(
+    output.code.contains("_react.default.createElement"),
+    "{}",
+    output.code
+  );
+}
+
+#[test]
+fn js_files_can_have_jsx() {
+  let output = transform_sync("const el = <div />;".into(), in_file("view.js")).unwrap();
+  assert!(output.diagnostics.is_none(), "{:?}", output.diagnostics);
+  assert!(
+    output.code.contains("React.createElement"),
+    "{}",
+    output.code
+  );
+}
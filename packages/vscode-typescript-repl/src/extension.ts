@@ -1,15 +1,36 @@
 console.log("HECKINNNN", require("../package.json").version)
 import './register'
 import {logger} from "./logger";
-import {createREPL, evaluate, repls} from "./repl";
+import {createREPL, evaluate, repls, restoreSession, saveSession} from "./repl";
+import * as fs from 'node:fs'
 import * as path from 'node:path'
 import * as vscode from 'vscode';
+import {clearLogger, setLogger} from "swc-ts-repl-transpile";
 
 let myREPL = createREPL({name: 'test-repl-id'})
 let chan = vscode.window.createOutputChannel("typescript-repl")
 let filepathsChangedSinceLastEvaluation = new Set<string>()
+let sessionFile: string | undefined
+
+function forwardTranspilerLogs() {
+  const maxLevel = vscode.workspace.getConfiguration("typescript-repl").get<string>("logLevel", "warn")
+  setLogger((level, target, message) => {
+    chan.appendLine(`[${level}] ${target}: ${message}`)
+  }, maxLevel as Parameters<typeof setLogger>[1])
+}
 
 export function activate(context: vscode.ExtensionContext) {
+  sessionFile = path.join(context.globalStorageUri.fsPath, "session.json")
+  if (fs.existsSync(sessionFile)) {
+    restoreSession(myREPL, sessionFile)
+  }
+
+  forwardTranspilerLogs()
+  context.subscriptions.push(vscode.workspace.onDidChangeConfiguration(e => {
+    if (e.affectsConfiguration("typescript-repl.logLevel")) {
+      forwardTranspilerLogs()
+    }
+  }))
 
   vscode.workspace.onWillSaveTextDocument(e => {
     logger.debug("Will save text document", e)
@@ -24,7 +45,7 @@ export function activate(context: vscode.ExtensionContext) {
     // Could be any file type.
     // The extension will try to support any file type,
     // but as far as we know dirty tracking is only relevant for js/ts i.e. files that can be required
-    if (e.document.languageId === 'typescript' || e.document.languageId === 'javascript') {
+    if (['typescript', 'typescriptreact', 'javascript', 'javascriptreact'].includes(e.document.languageId)) {
       // Note: other identifiers are available under document.uri if ever needed.
       // The contents of the set should match what is used in the require cache, expected to be an absolute filepath
       filepathsChangedSinceLastEvaluation.add(e.document.fileName)
@@ -78,7 +99,9 @@ export function activate(context: vscode.ExtensionContext) {
         code: text,
         filename: currentlyOpenTabFilePath,
         replId: myREPL.id,
-        __dirname: currentlyOpenTabDirname
+        __dirname: currentlyOpenTabDirname,
+        lineOffset: selection.start.line,
+        columnOffset: selection.start.character,
       }, undefined)
 
       if (result.type === 'error') {
@@ -147,6 +170,14 @@ export function activate(context: vscode.ExtensionContext) {
 // This method is called when your extension is deactivated
 export function deactivate() {
   console.log("deactivated")
+  if (sessionFile) {
+    try {
+      saveSession(myREPL, sessionFile)
+    } catch (e) {
+      console.error("Could not save repl session", e)
+    }
+  }
+  clearLogger()
   // chan.dispose()
 }
 
@@ -1,6 +1,7 @@
 import {addHook} from "pirates";
 import {transformSync} from "swc-ts-repl-transpile";
 import {logger} from "./logger";
+import {formatDiagnostics, hasErrors} from "./transpile";
 
 const matcher = (filename) => {
   return true
@@ -9,14 +10,24 @@ const matcher = (filename) => {
 
 const revert = addHook(
   (code, filename) => {
+    let output
     try {
-      return transformSync(code).code
+      output = transformSync(code, {filename})
     } catch (e) {
       logger.error('Require hook transformSync error', e)
+      throw e
     }
+    // the code of a module that doesn't parse is empty, so requiring it has
+    // to fail rather than give an empty module
+    if (hasErrors(output.diagnostics)) {
+      const message = `Require hook transformSync error in ${filename}\n${formatDiagnostics(output.diagnostics)}`
+      logger.error(message)
+      throw new Error(message)
+    }
+    return output.code
   },
   {
-    exts: ['.ts'],
+    exts: ['.ts', '.tsx', '.mts', '.cts', '.jsx'],
     matcher
   }
 )
@@ -2,7 +2,8 @@ import * as vm from "vm"
 import * as util from "util"
 import {assignGlobal, createREPLErrorHandlers, createRequire} from "./vm"
 import {Namespaces} from "./namespace"
-import {tsToJS} from "./transpile"
+import {formatDiagnostics, hasErrors, mapErrorStack, tsToJSInSession} from "./transpile"
+import {ReplSession} from "swc-ts-repl-transpile"
 import {Module} from "module"
 import {v4 as uuid} from "uuid"
 import * as R from "ramda"
@@ -64,6 +65,8 @@ export type REPL = {
   name?: string
   currentNamespace: string
   namespaces: Namespaces
+  // what the transpiler knows about each namespace's evaluations
+  session: ReplSession
   handlers: {
     process: {
       // argh. can't import these
@@ -211,6 +214,7 @@ export const createREPL = (args: {
     name: args.name,
     currentNamespace: "index.ts",
     namespaces: namespaces,
+    session: new ReplSession(),
     handlers: {process: processErrorHandlers},
   }
   repls.set(replId, repl)
@@ -286,24 +290,44 @@ export const evaluateSync = (args: EvaluateSyncInput, _socket: unknown): PrintRe
     namespace.context.console = regularConsole
   }
 
-  const jsCode = tsToJS(args.code)
-
-  const result = vm.runInContext(jsCode.code, namespace.context, {
-    columnOffset: args.columnOffset,
-    lineOffset: args.lineOffset,
+  const jsCode = tsToJSInSession(repl.session, args.filename, args.code, {
     filename: args.filename,
-    displayErrors: true,
+    sourceMaps: true,
+    lineOffset: args.lineOffset,
+    columnOffset: args.columnOffset,
   })
+  if (hasErrors(jsCode.diagnostics)) {
+    return {
+      type: "error",
+      text: formatDiagnostics(jsCode.diagnostics),
+      filename: args.filename,
+      input: {
+        type: "expr",
+        filename: args.filename,
+        code: args.code,
+      },
+    }
+  }
+
+  let result
+  try {
+    result = vm.runInContext(jsCode.code, namespace.context, {
+      filename: args.filename,
+      displayErrors: true,
+    })
+  } catch (e) {
+    throw mapErrorStack(e, jsCode, args.filename)
+  } finally {
+    if (oldConsole) {
+      namespace.context.console = oldConsole
+    }
+  }
 
   log("repl evaluate sync  run in context result", result)
 
   namespace.context["$2"] = namespace.context["$1"]
   namespace.context["$1"] = result
 
-  if (oldConsole) {
-    namespace.context.console = oldConsole
-  }
-
   return {
     type: "print" as const,
     result: print(result),
@@ -321,6 +345,9 @@ type EvaluateInput = {
   filename: string
   __dirname: string,
   code: string
+  // where the code starts in the document (0-based), used to map errors back to it
+  lineOffset?: number
+  columnOffset?: number
 }
 
 export const evaluate = async (args: EvaluateInput, socket: unknown): Promise<ErrorResult | PrintResult> => {
@@ -403,31 +430,44 @@ export const evaluate = async (args: EvaluateInput, socket: unknown): Promise<Er
 
   let jsCode
   try {
-    jsCode = tsToJS(args.code)
+    jsCode = tsToJSInSession(repl.session, filenameOrNamespace, args.code, {
+      filename: filenameOrNamespace,
+      sourceMaps: true,
+      lineOffset: args.lineOffset,
+      columnOffset: args.columnOffset,
+    })
   } catch (e) {
     console.error("tsToJS error", e)
     return e
   }
+  if (hasErrors(jsCode.diagnostics)) {
+    const error: ErrorResult = {
+      type: "error",
+      text: formatDiagnostics(jsCode.diagnostics),
+      filename: filenameOrNamespace,
+      input: {
+        type: "expr",
+        code: args.code,
+        filename: filenameOrNamespace,
+      },
+    }
+    api.emit(socket, "repl:output", error)
+    return error
+  }
 
   let ret
   try {
     const initialContext = Object.assign({}, namespace.context)
     let result
+    // positions in errors are mapped back to the document with the source map,
+    // which knows about code the transform moved around
     if (jsCode.isAsync) {
       result = await vm.runInContext(jsCode.code, namespace.context, {
-        // @ts-expect-error TODO
-        columnOffset: args.from,
-        // @ts-expect-error TODO
-        lineOffset: args.from,
         filename: filenameOrNamespace,
         displayErrors: true,
       })
     } else {
       result = vm.runInContext(jsCode.code, namespace.context, {
-        // @ts-expect-error TODO
-        columnOffset: args.from,
-        // @ts-expect-error TODO
-        lineOffset: args.from,
         filename: filenameOrNamespace,
         displayErrors: true,
       })
@@ -477,6 +517,7 @@ export const evaluate = async (args: EvaluateInput, socket: unknown): Promise<Er
 
     // catch for error in the evaluated code
   } catch (e) {
+    mapErrorStack(e, jsCode, filenameOrNamespace)
     log("repl:output", e)
     // if (e instanceof SyntaxError) {
     //   if (
@@ -547,11 +588,26 @@ export const reset = (args: ResetInput, socket: unknown) => {
     return
   }
   repl.namespaces = {}
+  repl.session.reset()
   repl.currentNamespace = undefined
   api.emit(socket, "repl:reset", {replId: args.replId})
   return {replId: args.replId}
 }
 
+// Keeps each namespace's options and history across restarts of the extension
+// host. Their contexts are not restored, so neither is what the transpiler
+// knew was defined in them, like bindings, helpers and const enums.
+export const saveSession = (repl: REPL, file: string) => {
+  repl.session.save(file)
+}
+export const restoreSession = (repl: REPL, file: string) => {
+  try {
+    repl.session = ReplSession.load(file)
+  } catch (e) {
+    console.error("Could not restore repl session", e)
+  }
+}
+
 export type NSUnmapInput = {
   replId: string;
   namespace: string;
@@ -576,6 +632,7 @@ export const nsUnmap = (args: NSUnmapInput, socket: unknown) => {
   }
   namespace.defs = namespace.defs.filter((x) => x !== args.symbol)
   delete namespace.context[args.symbol]
+  repl.session.unmap(args.namespace, args.symbol)
   const output = {
     replId: args.replId,
     namespace: args.namespace,
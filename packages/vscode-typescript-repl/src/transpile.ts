@@ -1,7 +1,53 @@
-import { TransformOutput, transformSync } from "swc-ts-repl-transpile"
+import {
+  Diagnostic,
+  mapStackTrace,
+  ReplSession,
+  SessionTransformOutput,
+  TransformOptions,
+  TransformOutput,
+  transformSync
+} from "swc-ts-repl-transpile"
 
-export const tsToJS = (code: string): TransformOutput => {
-  const result = transformSync(code)
+export const tsToJS = (code: string, options?: TransformOptions): TransformOutput => {
+  const result = transformSync(code, options)
   console.log("transform result", result)
   return result
 }
+
+// Like tsToJS, for code evaluated in a namespace the session has seen before
+export const tsToJSInSession = (
+  session: ReplSession,
+  namespace: string,
+  code: string,
+  options?: TransformOptions
+): SessionTransformOutput => {
+  const result = session.transform(namespace, code, options)
+  console.log("transform result", result)
+  return result
+}
+
+export const hasErrors = (diagnostics: Diagnostic[] = []) =>
+  diagnostics.some((d) => d.severity === "error")
+
+export const formatDiagnostics = (diagnostics: Diagnostic[] = []) =>
+  diagnostics
+    .map((d) =>
+      d.span
+        ? `${d.severity}: ${d.message} (${d.span.startLine}:${d.span.startColumn + 1})`
+        : `${d.severity}: ${d.message}`
+    )
+    .join("\n")
+
+// Points frames from an evaluation at the original TypeScript when we have a
+// source map for it. Mutates the error so printing it shows the mapped stack.
+export const mapErrorStack = (e: unknown, output: TransformOutput, filename: string) => {
+  if (!output.map || !(e instanceof Object) || typeof (e as Error).stack !== "string") {
+    return e
+  }
+  try {
+    (e as Error).stack = mapStackTrace((e as Error).stack, output.map, filename).stack
+  } catch (mapError) {
+    console.error("mapStackTrace error", mapError)
+  }
+  return e
+}